@@ -24,7 +24,7 @@ pub async fn run() {
 
         if let Ok(tools) = tools_res {
             let ping_start = Instant::now();
-            let _ = manager.call_method(&name, "ping", serde_json::json!({})).await;
+            let _ = manager.call_method(&name, "ping", serde_json::json!({}), None).await;
             let ping_duration = ping_start.elapsed().as_millis();
 
             // Placeholder for RAM since accurate process tree measuring is complex in Rust without sysinfo crate
@@ -0,0 +1,275 @@
+//! Per-server circuit breakers (`"circuitBreaker"` in config).
+//! A server that keeps failing would otherwise cost every call the full start
+//! retry ladder and a restart-and-retry. Once its circuit opens, calls fail
+//! fast until the cool-down ends; then a single trial call decides whether it
+//! closes again (success) or stays open for another cool-down (failure).
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::CircuitBreaker;
+
+/// Calls older than this no longer count towards the error rate.
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State {
+    Closed,
+    Open,
+    /// Cool-down over; one trial call is allowed through.
+    HalfOpen,
+}
+
+impl State {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            State::Closed => "closed",
+            State::Open => "open",
+            State::HalfOpen => "half_open",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Breaker {
+    state: State,
+    consecutive_failures: u32,
+    /// (when, failed) for calls within `RATE_WINDOW`.
+    recent: VecDeque<(Instant, bool)>,
+    opened_at: Instant,
+    cooldown: Duration,
+    /// Why the circuit last opened.
+    reason: String,
+    trial_in_flight: bool,
+}
+
+impl Breaker {
+    fn new() -> Self {
+        Self {
+            state: State::Closed,
+            consecutive_failures: 0,
+            recent: VecDeque::new(),
+            opened_at: Instant::now(),
+            cooldown: Duration::ZERO,
+            reason: String::new(),
+            trial_in_flight: false,
+        }
+    }
+
+    fn retry_after(&self) -> Duration {
+        self.cooldown.saturating_sub(self.opened_at.elapsed())
+    }
+
+    fn open(&mut self, name: &str, config: &CircuitBreaker, reason: String) {
+        self.state = State::Open;
+        self.opened_at = Instant::now();
+        self.cooldown = Duration::from_secs(config.cooldown_secs);
+        self.trial_in_flight = false;
+        eprintln!("[McpHub][WARN] Circuit open for {}: {}. Calls fail fast for {}s", name, reason, config.cooldown_secs);
+        self.reason = reason;
+    }
+
+    fn push(&mut self, failed: bool) {
+        let now = Instant::now();
+        while self.recent.front().is_some_and(|(at, _)| now.duration_since(*at) > RATE_WINDOW) {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((now, failed));
+    }
+
+    /// Why the circuit should open now, if it should.
+    fn trip_reason(&self, config: &CircuitBreaker, last_error: &str) -> Option<String> {
+        // Start errors carry a stderr tail; the first line says enough
+        let last_error = last_error.lines().next().unwrap_or_default();
+        if self.consecutive_failures >= config.failures {
+            return Some(format!("{} consecutive failures, last: {}", self.consecutive_failures, last_error));
+        }
+        let calls = self.recent.len();
+        let failed = self.recent.iter().filter(|(_, f)| *f).count();
+        if calls >= config.min_calls as usize && failed as f64 / calls as f64 >= config.error_rate {
+            return Some(format!("{} of the last {} calls failed, last: {}", failed, calls, last_error));
+        }
+        None
+    }
+}
+
+/// Circuit state, seconds until the next trial and counts, as shown in metrics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Status {
+    pub state: State,
+    pub retry_after_secs: u64,
+    /// Times the circuit opened.
+    pub trips: u64,
+    /// Calls failed fast while it was open.
+    pub rejected: u64,
+}
+
+/// Per-server counts; kept when a breaker is reset.
+#[derive(Debug, Default, Clone, Copy)]
+struct Counts {
+    trips: u64,
+    rejected: u64,
+}
+
+#[derive(Default)]
+pub struct Breakers {
+    map: Mutex<HashMap<String, Breaker>>,
+    counts: Mutex<HashMap<String, Counts>>,
+}
+
+impl Breakers {
+    /// Let a call to `name` through, or refuse it while the circuit is open.
+    pub fn admit<'a>(&'a self, name: &str, config: &CircuitBreaker) -> Result<Attempt<'a>, String> {
+        let mut attempt = Attempt { breakers: self, name: name.to_string(), config: config.clone(), trial: false, recorded: false };
+        if !config.enabled {
+            return Ok(attempt);
+        }
+        let mut map = self.map.lock().unwrap_or_else(|e| e.into_inner());
+        let breaker = map.entry(name.to_string()).or_insert_with(Breaker::new);
+        match breaker.state {
+            State::Closed => {}
+            State::Open if breaker.retry_after().is_zero() => {
+                eprintln!("[McpHub][INFO] Circuit half-open for {}: trying one call", name);
+                breaker.state = State::HalfOpen;
+                breaker.trial_in_flight = true;
+                attempt.trial = true;
+            }
+            State::HalfOpen if !breaker.trial_in_flight => {
+                breaker.trial_in_flight = true;
+                attempt.trial = true;
+            }
+            State::Open => {
+                self.count(name, |c| c.rejected += 1);
+                return Err(format!(
+                    "Server {} unavailable ({}), retry after {}s",
+                    name, breaker.reason, breaker.retry_after().as_secs().max(1)
+                ));
+            }
+            State::HalfOpen => {
+                self.count(name, |c| c.rejected += 1);
+                return Err(format!("Server {} unavailable (checking whether it recovered), retry after 1s", name));
+            }
+        }
+        Ok(attempt)
+    }
+
+    /// Forget the history of `name`, e.g. after its config changed.
+    pub fn reset(&self, name: &str) {
+        self.map.lock().unwrap_or_else(|e| e.into_inner()).remove(name);
+    }
+
+    fn count(&self, name: &str, update: impl FnOnce(&mut Counts)) {
+        update(self.counts.lock().unwrap_or_else(|e| e.into_inner()).entry(name.to_string()).or_default());
+    }
+
+    /// Servers whose circuit is not closed or has ever opened.
+    pub fn statuses(&self) -> Vec<(String, Status)> {
+        let map = self.map.lock().unwrap_or_else(|e| e.into_inner());
+        let counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        let open = map.iter().filter(|(_, b)| b.state != State::Closed).map(|(name, _)| name);
+        let mut names: Vec<&String> = open.chain(counts.keys()).collect();
+        names.sort();
+        names.dedup();
+        names
+            .into_iter()
+            .map(|name| {
+                let (state, retry_after_secs) = match map.get(name) {
+                    Some(b) => (b.state, b.retry_after().as_secs()),
+                    None => (State::Closed, 0),
+                };
+                let c = counts.get(name).copied().unwrap_or_default();
+                (name.clone(), Status { state, retry_after_secs, trips: c.trips, rejected: c.rejected })
+            })
+            .collect()
+    }
+}
+
+/// A call admitted by `Breakers::admit`; report how it went with `record`.
+pub struct Attempt<'a> {
+    breakers: &'a Breakers,
+    name: String,
+    config: CircuitBreaker,
+    trial: bool,
+    recorded: bool,
+}
+
+impl Attempt<'_> {
+    /// `failure` is the error, if the call failed in a way that counts against the server.
+    pub fn record(mut self, failure: Option<&str>) {
+        self.recorded = true;
+        if !self.config.enabled {
+            return;
+        }
+        let mut map = self.breakers.map.lock().unwrap_or_else(|e| e.into_inner());
+        let breaker = map.entry(self.name.clone()).or_insert_with(Breaker::new);
+        breaker.push(failure.is_some());
+        let was_open = breaker.state == State::Open;
+
+        match failure {
+            None if self.trial => {
+                eprintln!("[McpHub][INFO] Circuit closed for {}: trial call succeeded", self.name);
+                *breaker = Breaker::new();
+            }
+            None => breaker.consecutive_failures = 0,
+            Some(error) if self.trial => {
+                breaker.consecutive_failures += 1;
+                let error = error.lines().next().unwrap_or_default();
+                breaker.open(&self.name, &self.config, format!("trial call failed: {}", error));
+            }
+            Some(error) => {
+                breaker.consecutive_failures += 1;
+                if breaker.state == State::Closed {
+                    if let Some(reason) = breaker.trip_reason(&self.config, error) {
+                        breaker.open(&self.name, &self.config, reason);
+                    }
+                }
+            }
+        }
+        if !was_open && breaker.state == State::Open {
+            drop(map);
+            self.breakers.count(&self.name, |c| c.trips += 1);
+        }
+    }
+}
+
+impl Drop for Attempt<'_> {
+    /// A trial call that never reported (its client went away) lets the next one try.
+    fn drop(&mut self) {
+        if self.trial && !self.recorded {
+            let mut map = self.breakers.map.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(breaker) = map.get_mut(&self.name) {
+                breaker.trial_in_flight = false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_opens_and_recovers() {
+        let breakers = Breakers::default();
+        let config = CircuitBreaker { failures: 3, cooldown_secs: 0, ..Default::default() };
+
+        for _ in 0..3 {
+            breakers.admit("db", &config).unwrap().record(Some("Timeout"));
+        }
+        assert_eq!(breakers.statuses()[0].1.state, State::Open);
+
+        // Cool-down of 0s: the next call is the trial, concurrent ones are refused
+        let trial = breakers.admit("db", &config).unwrap();
+        assert!(breakers.admit("db", &config).err().unwrap().contains("retry after"));
+        trial.record(None);
+        let status = Status { state: State::Closed, retry_after_secs: 0, trips: 1, rejected: 1 };
+        assert_eq!(breakers.statuses(), vec![("db".to_string(), status)]);
+
+        let disabled = CircuitBreaker { enabled: false, ..config };
+        for _ in 0..5 {
+            breakers.admit("off", &disabled).unwrap().record(Some("Timeout"));
+        }
+        assert!(breakers.admit("off", &disabled).is_ok());
+    }
+}
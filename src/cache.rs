@@ -65,6 +65,21 @@ pub fn repair_server_cache(name: &str, tools: Vec<ToolDef>) {
     }
 }
 
+/// Record why a running server went down, keeping its cached tools so it can
+/// still be discovered and restarted. Cleared when its tools are cached again.
+pub fn note_server_error(name: &str, error: &str) {
+    let mut cache = match load_cache() {
+        Some(cache) => cache,
+        None => return,
+    };
+    cache.errors.insert(name.to_string(), error.to_string());
+    if let Some(path) = cache_path() {
+        if let Ok(json) = serde_json::to_string_pretty(&cache) {
+            let _ = fs::write(&path, json);
+        }
+    }
+}
+
 /// Store an error for a server in cache
 pub fn set_server_error(name: &str, error: &str) {
     let mut cache = load_cache().unwrap_or_else(|| SchemaCache {
@@ -1,68 +1,462 @@
 /// Child process manager: spawn MCP servers, communicate over stdio, manage lifecycle.
+/// Each instance has one reader that dispatches responses by JSON-RPC id, so many
+/// requests can be in flight on the same connection at once.
 use std::collections::HashMap;
 use std::process::Stdio;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 use std::time::Instant;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::process::{Child, Command};
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, oneshot, Mutex};
 
 pub use crate::config::ServerConfig;
-use crate::protocol::ToolDef;
+use crate::breaker::{self, Breakers};
+use crate::coalesce::{self, Flight, Flights};
+use crate::config::{CircuitBreaker, Limits, Routing, Scope, Throttle, Timeouts, Transport};
+use crate::peer::{request_key, Caller, ClientPeer};
+use crate::limits;
+use crate::procgroup;
+use crate::sandbox;
+use crate::secrets;
+use crate::protocol::{ToolDef, LATEST_PROTOCOL_VERSION, SUPPORTED_PROTOCOL_VERSIONS};
+use crate::remote::{LegacySseClient, StreamableHttpClient};
+use crate::result_cache::{self, ResultCache};
+use crate::stderr::{self, StderrBuffers};
+use crate::throttle::{self, Throttles};
+
+/// The wire to one upstream instance: a spawned process or a remote endpoint.
+#[derive(Debug)]
+enum Link {
+    Stdio {
+        /// Taken on shutdown, so stopping it does not hold the lock.
+        child: Box<Mutex<Option<Child>>>,
+        /// Taken on shutdown; servers are expected to exit on EOF.
+        stdin: Mutex<Option<tokio::process::ChildStdin>>,
+        /// The child leads its own process group (unix), so wrappers such as
+        /// `npx` are stopped together with what they started.
+        pgid: Option<u32>,
+        /// Set by the limits watchdog when it kills the group.
+        limit_exceeded: Arc<std::sync::Mutex<Option<String>>>,
+        /// Copies stderr into the server's ring buffer; finishes at EOF.
+        stderr_task: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    },
+    StreamableHttp(StreamableHttpClient),
+    Sse(LegacySseClient),
+}
 
+/// A caller waiting for a response, and the client it is calling on behalf of.
 #[derive(Debug)]
-struct ChildProcess {
-    child: Child,
-    stdin: tokio::process::ChildStdin,
-    stdout_lines: Arc<Mutex<tokio::io::Lines<BufReader<tokio::process::ChildStdout>>>>,
-    next_id: u64,
-    tools: Vec<ToolDef>,
-    last_used: Instant,
+struct Pending {
+    tx: oneshot::Sender<Result<serde_json::Value, String>>,
+    caller: Option<Caller>,
+    /// The client's own progress token; upstream sees our request id instead.
+    progress_token: Option<serde_json::Value>,
+}
+
+/// Callers waiting for a response, keyed by the request id we sent upstream.
+type PendingMap = Arc<Mutex<HashMap<u64, Pending>>>;
+
+/// A request the upstream sent us, with the client request it should be relayed
+/// under, or why there is none.
+type ServerRequest = (serde_json::Value, Result<Caller, String>);
+
+/// Receiving side of one upstream instance. Whatever reads from the transport
+/// (stdout reader task, SSE stream, POST response) hands every message here.
+#[derive(Debug, Clone)]
+pub struct Inbox {
     server_name: String,
-    protocol_version: String,
+    pending: PendingMap,
+    closed: Arc<AtomicBool>,
+    server_requests: mpsc::UnboundedSender<ServerRequest>,
+    /// Client of the most recent call, for requests that arrive between calls.
+    last_peer: Arc<std::sync::Mutex<Weak<ClientPeer>>>,
+    /// Told the server name on `notifications/tools/list_changed`.
+    tools_changed: mpsc::UnboundedSender<String>,
+}
+
+impl Inbox {
+    fn new(
+        server_name: &str,
+        tools_changed: mpsc::UnboundedSender<String>,
+    ) -> (Self, mpsc::UnboundedReceiver<ServerRequest>) {
+        let (server_requests, rx) = mpsc::unbounded_channel();
+        let inbox = Self {
+            server_name: server_name.to_string(),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            closed: Arc::new(AtomicBool::new(false)),
+            server_requests,
+            last_peer: Arc::new(std::sync::Mutex::new(Weak::new())),
+            tools_changed,
+        };
+        (inbox, rx)
+    }
+
+    pub fn server_name(&self) -> &str {
+        &self.server_name
+    }
+
+    /// Route one incoming message: responses go to their waiting caller, server
+    /// requests are queued for relay to a client, progress goes to the client of
+    /// the call it reports on, tool list changes are announced, other
+    /// notifications are logged.
+    /// `origin` is the request whose response stream carried the message, if known.
+    pub async fn dispatch(&self, msg: serde_json::Value, origin: Option<u64>) {
+        if crate::peer::is_response(&msg) {
+            if let Some(id) = msg.get("id").and_then(|v| v.as_u64()) {
+                if let Some(pending) = self.pending.lock().await.remove(&id) {
+                    let _ = pending.tx.send(Ok(msg));
+                }
+            }
+            return;
+        }
+
+        if msg.get("id").is_some() {
+            let method = msg.get("method").and_then(|v| v.as_str()).unwrap_or("");
+            let caller = self.originating_caller(origin, method).await;
+            let _ = self.server_requests.send((msg, caller));
+            return;
+        }
+
+        match msg.get("method").and_then(|v| v.as_str()) {
+            Some("notifications/progress") => {
+                self.forward_progress(msg).await;
+                return;
+            }
+            Some("notifications/tools/list_changed") => {
+                let _ = self.tools_changed.send(self.server_name.clone());
+                return;
+            }
+            _ => {}
+        }
+
+        log_child_message(&self.server_name, &msg);
+    }
+
+    /// Send a progress notification to the client under the token it chose.
+    /// Progress for calls that already finished (or never asked for it) is dropped.
+    async fn forward_progress(&self, mut msg: serde_json::Value) {
+        let id = match msg.pointer("/params/progressToken").and_then(|v| v.as_u64()) {
+            Some(id) => id,
+            None => return,
+        };
+        let target = {
+            let pending = self.pending.lock().await;
+            pending.get(&id).and_then(|p| Some((p.caller.clone()?, p.progress_token.clone()?)))
+        };
+        if let Some((caller, token)) = target {
+            msg["params"]["progressToken"] = token;
+            let _ = caller.peer.send_for(caller.request_id.as_ref(), &msg).await;
+        }
+    }
+
+    /// The client call a server request belongs to: the call whose stream carried
+    /// it, else the newest in-flight call while all of them are one client's,
+    /// else whoever called last (outside any call). Calls from several clients
+    /// in flight leave it unroutable, rather than handing it to a guess.
+    async fn originating_caller(&self, origin: Option<u64>, method: &str) -> Result<Caller, String> {
+        {
+            let pending = self.pending.lock().await;
+            if let Some(caller) = origin.and_then(|id| pending.get(&id)).and_then(|p| p.caller.clone()) {
+                return Ok(caller);
+            }
+            let callers: Vec<(u64, &Caller)> = pending
+                .iter()
+                .filter_map(|(id, p)| p.caller.as_ref().map(|caller| (*id, caller)))
+                .collect();
+            if let Some((_, newest)) = callers.iter().max_by_key(|(id, _)| *id) {
+                if callers.iter().any(|(_, c)| c.peer.id() != newest.peer.id()) {
+                    return Err("cannot route server request".to_string());
+                }
+                return Ok((*newest).clone());
+            }
+        }
+        let peer = self.last_peer.lock().ok().and_then(|w| w.upgrade());
+        peer.map(|peer| Caller { peer, request_id: None })
+            .ok_or_else(|| format!("No client connected to handle {}", method))
+    }
+
+    async fn register(
+        &self,
+        id: u64,
+        tx: oneshot::Sender<Result<serde_json::Value, String>>,
+        caller: Option<&Caller>,
+        progress_token: Option<serde_json::Value>,
+    ) {
+        if let Some(caller) = caller {
+            if let Ok(mut last) = self.last_peer.lock() {
+                *last = Arc::downgrade(&caller.peer);
+            }
+        }
+        self.pending.lock().await.insert(id, Pending { tx, caller: caller.cloned(), progress_token });
+    }
+
+    /// Give up on `id`: its caller sees a closed connection.
+    pub async fn forget(&self, id: u64) {
+        self.pending.lock().await.remove(&id);
+    }
+
+    /// End the call waiting on `id` with a cancelled error.
+    async fn cancel(&self, id: u64, reason: &str) {
+        if let Some(pending) = self.pending.lock().await.remove(&id) {
+            let _ = pending.tx.send(Err(format!("Cancelled: {}", reason)));
+        }
+    }
+
+    /// The transport is gone: fail every in-flight request.
+    pub async fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+        self.pending.lock().await.clear();
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+
+}
+
+#[derive(Debug)]
+struct ChildProcess {
+    link: Link,
+    inbox: Inbox,
+    next_id: AtomicU64,
+    last_used: std::sync::Mutex<Instant>,
+    /// Negotiated on `initialize`.
+    protocol_version: &'static str,
+    timeouts: Timeouts,
+    /// Calls outstanding on this instance (its queue depth).
+    active: AtomicUsize,
+}
+
+/// Counts a call against its instance while it is outstanding.
+struct ActiveCall<'a>(&'a AtomicUsize);
+
+impl<'a> ActiveCall<'a> {
+    fn new(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Self(counter)
+    }
+}
+
+impl Drop for ActiveCall<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A session's claim on one instance under sticky routing. Lapses when the
+/// session's peer is dropped, i.e. when the client disconnects.
+struct Pin {
+    session: Weak<ClientPeer>,
+    proc: Weak<ChildProcess>,
 }
 
+/// Running instances of one server. Grows up to `pool.max` while calls queue
+/// up and is trimmed back to `pool.min` by the idle reaper.
 struct ServerPool {
-    procs: Vec<Arc<Mutex<ChildProcess>>>,
+    procs: std::sync::RwLock<Vec<Arc<ChildProcess>>>,
     next_idx: AtomicUsize,
+    /// Sticky routing: session id to its instance.
+    pins: std::sync::Mutex<HashMap<u64, Pin>>,
+    /// As listed at start, or re-listed after `notifications/tools/list_changed`.
+    tools: std::sync::Mutex<Vec<ToolDef>>,
+    /// The config the pool was started with; extra instances use it too.
+    config: ServerConfig,
+    /// An extra instance is being started.
+    scaling: AtomicBool,
 }
 
+impl ServerPool {
+    fn new(procs: Vec<Arc<ChildProcess>>, tools: Vec<ToolDef>, config: ServerConfig) -> Self {
+        Self {
+            procs: std::sync::RwLock::new(procs),
+            next_idx: AtomicUsize::new(0),
+            pins: std::sync::Mutex::new(HashMap::new()),
+            tools: std::sync::Mutex::new(tools),
+            config,
+            scaling: AtomicBool::new(false),
+        }
+    }
+
+    fn tools(&self) -> Vec<ToolDef> {
+        self.tools.lock().map(|t| t.clone()).unwrap_or_default()
+    }
+
+    /// Snapshot of the current instances.
+    fn instances(&self) -> Vec<Arc<ChildProcess>> {
+        self.procs.read().map(|p| p.clone()).unwrap_or_default()
+    }
+
+    /// Pick an instance by the server's `routing` and mark it used. Calls
+    /// without a session are routed round-robin under sticky routing.
+    fn pick(&self, session: Option<&Arc<ClientPeer>>) -> Arc<ChildProcess> {
+        let procs = self.procs.read().unwrap_or_else(|e| e.into_inner());
+        let proc = match (self.config.routing, session) {
+            (Routing::Sticky, Some(peer)) => self.pinned(&procs, peer),
+            (Routing::LeastBusy, _) => self.least_busy(&procs),
+            _ => procs[self.next_idx.fetch_add(1, Ordering::Relaxed) % procs.len()].clone(),
+        };
+        proc.touch();
+        proc
+    }
+
+    /// The first idle instance after the round-robin cursor, else the one
+    /// with the fewest calls outstanding.
+    fn least_busy(&self, procs: &[Arc<ChildProcess>]) -> Arc<ChildProcess> {
+        let start = self.next_idx.fetch_add(1, Ordering::Relaxed);
+        let rotated = (0..procs.len()).map(|i| &procs[(start + i) % procs.len()]);
+        let mut best: Option<&Arc<ChildProcess>> = None;
+        for proc in rotated {
+            if proc.load() == 0 {
+                return proc.clone();
+            }
+            if best.is_none_or(|b| proc.load() < b.load()) {
+                best = Some(proc);
+            }
+        }
+        best.unwrap_or(&procs[0]).clone()
+    }
+
+    /// The instance `peer` is pinned to. A new session, or one whose instance
+    /// has gone, is pinned to the least busy instance.
+    fn pinned(&self, procs: &[Arc<ChildProcess>], peer: &Arc<ClientPeer>) -> Arc<ChildProcess> {
+        let mut pins = self.live_pins();
+        let current = pins.get(&peer.id()).and_then(|pin| pin.proc.upgrade());
+        if let Some(proc) = current.filter(|c| procs.iter().any(|p| Arc::ptr_eq(p, c))) {
+            return proc;
+        }
+        let proc = self.least_busy(procs);
+        pins.insert(peer.id(), Pin { session: Arc::downgrade(peer), proc: Arc::downgrade(&proc) });
+        proc
+    }
+
+    /// The pin table, with the pins of ended sessions released.
+    fn live_pins(&self) -> std::sync::MutexGuard<'_, HashMap<u64, Pin>> {
+        let mut pins = self.pins.lock().unwrap_or_else(|e| e.into_inner());
+        pins.retain(|_, pin| pin.session.strong_count() > 0 && pin.proc.strong_count() > 0);
+        pins
+    }
+
+    /// Every instance already has `scaleUpQueueDepth` calls outstanding and
+    /// the pool may still grow.
+    fn saturated(&self) -> bool {
+        let procs = self.procs.read().unwrap_or_else(|e| e.into_inner());
+        let limits = &self.config.pool;
+        procs.len() < limits.max && procs.iter().all(|p| p.load() >= limits.scale_up_queue_depth)
+    }
+
+    /// Calls outstanding across all instances.
+    fn queue_depth(&self) -> usize {
+        self.instances().iter().map(|p| p.load()).sum()
+    }
+
+    /// Add an instance; returns the new instance count.
+    fn add(&self, proc: Arc<ChildProcess>) -> usize {
+        let mut procs = self.procs.write().unwrap_or_else(|e| e.into_inner());
+        procs.push(proc);
+        procs.len()
+    }
+
+    /// Take instances idle for longer than `idle` out of rotation, keeping
+    /// `pool.min`. An instance a live session is pinned to keeps its state.
+    fn remove_idle(&self, idle: std::time::Duration) -> Vec<Arc<ChildProcess>> {
+        let mut procs = self.procs.write().unwrap_or_else(|e| e.into_inner());
+        let pinned: Vec<*const ChildProcess> = self.live_pins().values().map(|pin| pin.proc.as_ptr()).collect();
+        let mut removed = Vec::new();
+        while procs.len() > self.config.pool.min.max(1) {
+            let candidate = procs.iter().rposition(|p| {
+                p.load() == 0 && p.idle_for() > idle && !pinned.contains(&Arc::as_ptr(p))
+            });
+            match candidate {
+                Some(idx) => removed.push(procs.remove(idx)),
+                None => break,
+            }
+        }
+        removed
+    }
+}
+
+/// Where a client request is being served: the instance and the id it sees.
+struct InFlight {
+    server: String,
+    proc: Weak<ChildProcess>,
+    child_id: u64,
+}
+
+/// Private instances of `"scope": "session"` servers, by (server, session id).
+type SessionPools = HashMap<(String, u64), Arc<ServerPool>>;
+
 pub struct ChildManager {
     configs: Arc<Mutex<HashMap<String, ServerConfig>>>,
     pools: Arc<Mutex<HashMap<String, Arc<ServerPool>>>>,
+    sessions: Arc<Mutex<SessionPools>>,
+    stderr: Arc<StderrBuffers>,
+    /// Keyed by (session, client request id), for routing cancellations.
+    in_flight: Mutex<HashMap<(u64, String), InFlight>>,
+    tools_changed: mpsc::UnboundedSender<String>,
+    tools_changed_rx: std::sync::Mutex<Option<mpsc::UnboundedReceiver<String>>>,
     idle_timeout_ms: u64,
+    breakers: Breakers,
+    throttles: Throttles,
+    results: ResultCache,
+    flights: Flights,
 }
 
 impl ChildManager {
     pub fn new(configs: HashMap<String, ServerConfig>, idle_timeout_ms: u64) -> Self {
+        let (tools_changed, tools_changed_rx) = mpsc::unbounded_channel();
         Self {
             configs: Arc::new(Mutex::new(configs)),
             pools: Arc::new(Mutex::new(HashMap::new())),
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            stderr: Arc::new(StderrBuffers::default()),
+            in_flight: Mutex::new(HashMap::new()),
+            tools_changed,
+            tools_changed_rx: std::sync::Mutex::new(Some(tools_changed_rx)),
             idle_timeout_ms,
+            breakers: Breakers::default(),
+            throttles: Throttles::default(),
+            results: ResultCache::default(),
+            flights: Flights::default(),
         }
     }
 
     pub async fn update_configs(&self, new_configs: HashMap<String, ServerConfig>) {
-        let mut current_configs = self.configs.lock().await;
-        
-        let mut to_stop = Vec::new();
-        for (name, old_cfg) in current_configs.iter() {
-            if let Some(new_cfg) = new_configs.get(name) {
-                if old_cfg != new_cfg {
-                    to_stop.push(name.clone());
-                }
-            } else {
-                to_stop.push(name.clone());
+        // Swap configs and take changed servers out of rotation in one step, so
+        // no call starts an old config; shutting them down happens unlocked
+        let mut stopping = Vec::new();
+        {
+            let mut current_configs = self.configs.lock().await;
+            let to_stop: Vec<String> = current_configs
+                .iter()
+                .filter(|(name, old_cfg)| new_configs.get(*name) != Some(*old_cfg))
+                .map(|(name, _)| name.clone())
+                .collect();
+            for name in to_stop {
+                let (running, procs) = self.take_server(&name).await;
+                stopping.push((name, running, procs));
             }
+            *current_configs = new_configs;
         }
 
-        for name in to_stop {
-            self.stop_server(&name).await;
+        let mut procs = Vec::new();
+        for (name, running, instances) in stopping {
+            self.breakers.reset(&name);
+            self.throttles.reset(&name);
+            self.results.invalidate(&name);
+            if running {
+                eprintln!("[McpHub][INFO] Stopping server: {} (config changed)", name);
+            }
+            procs.extend(instances);
         }
+        shutdown_all(procs).await;
+    }
 
-        *current_configs = new_configs;
+    /// Names of servers that sent `notifications/tools/list_changed`.
+    /// There is one receiver; the first caller gets it.
+    pub fn take_tools_changed(&self) -> Option<mpsc::UnboundedReceiver<String>> {
+        self.tools_changed_rx.lock().ok()?.take()
     }
 
     async fn resolve_name(&self, name: &str) -> Option<String> {
@@ -94,15 +488,15 @@ impl ChildManager {
         {
             let pools = self.pools.lock().await;
             if let Some(pool) = pools.get(name) {
-                let mut proc = pool.procs[0].lock().await;
-                proc.last_used = Instant::now();
-                return Ok(proc.tools.clone());
+                pool.pick(None);
+                return Ok(pool.tools());
             }
         }
 
         const MAX_RETRIES: u32 = 3;
         const BACKOFF_MS: [u64; 3] = [500, 1000, 2000];
         let mut last_error = String::new();
+        let mut stderr_mark = 0;
 
         for attempt in 0..MAX_RETRIES {
             if attempt > 0 {
@@ -111,6 +505,7 @@ impl ChildManager {
                 tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
             }
 
+            stderr_mark = self.stderr.mark(name);
             match self.try_start_pool(name).await {
                 Ok(tools) => return Ok(tools),
                 Err(e) => {
@@ -122,7 +517,9 @@ impl ChildManager {
             }
         }
 
-        Err(format!("{} (after {} attempts)", last_error, MAX_RETRIES))
+        let message = format!("{} (after {} attempts)", last_error, MAX_RETRIES);
+        let tail = self.stderr.since(name, stderr_mark, stderr::TAIL_LINES);
+        Err(StderrBuffers::annotate(&message, &tail))
     }
 
     async fn try_start_pool(&self, name: &str) -> Result<Vec<ToolDef>, String> {
@@ -131,8 +528,8 @@ impl ChildManager {
             configs.get(name).ok_or_else(|| format!("Unknown server: {}", name))?.clone()
         };
 
-        let pool_size = config.pool.max(1);
-        let mut procs = Vec::new();
+        let pool_size = config.pool.min.max(1);
+        let mut procs: Vec<Arc<ChildProcess>> = Vec::new();
         let mut first_tools = Vec::new();
 
         for i in 0..pool_size {
@@ -143,178 +540,482 @@ impl ChildManager {
                 eprintln!("[McpHub][INFO] Starting server: {}", name);
             }
 
-            let mut cmd = Command::new(&config.command);
-            cmd.args(&config.args)
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::null());
-
-            for (k, v) in &config.env {
-                cmd.env(k, v);
-            }
-
-            let mut child = cmd.spawn().map_err(|e| format!("Failed to spawn {}: {}", name, e))?;
-            let stdin = child.stdin.take().ok_or("No stdin")?;
-            let stdout = child.stdout.take().ok_or("No stdout")?;
-
-            let reader = BufReader::new(stdout);
-            let lines = Arc::new(Mutex::new(reader.lines()));
-
-            let mut proc = ChildProcess {
-                child,
-                stdin,
-                stdout_lines: lines,
-                next_id: 1,
-                tools: Vec::new(),
-                last_used: Instant::now(),
-                server_name: name.to_string(),
-                protocol_version: "2024-11-05".to_string(),
-            };
-
-            let init_result = send_request(
-                &mut proc,
-                "initialize",
-                serde_json::json!({
-                    "protocolVersion": "2024-11-05",
-                    "capabilities": {},
-                    "clientInfo": { "name": "McpHub", "version": "4.0.0" }
-                }),
-            )
-            .await?;
-
-            if let Some(pv) = init_result.get("protocolVersion").and_then(|v| v.as_str()) {
-                proc.protocol_version = pv.to_string();
-                if i == 0 {
-                    eprintln!("[McpHub][INFO] Server '{}' negotiated protocol: {}", name, pv);
+            let (proc, tools) = match start_instance(name, &config, &self.stderr, &self.tools_changed).await {
+                Ok(started) => started,
+                Err(e) => {
+                    for proc in &procs {
+                        proc.shutdown().await;
+                    }
+                    return Err(e);
                 }
-            }
-
-            send_notification(&mut proc, "notifications/initialized", serde_json::json!({})).await?;
-            let tools_result = send_request(&mut proc, "tools/list", serde_json::json!({})).await?;
-            let tools: Vec<ToolDef> = tools_result
-                .get("tools")
-                .and_then(|v| serde_json::from_value(v.clone()).ok())
-                .unwrap_or_default();
+            };
 
             if i == 0 {
                 let elapsed = start.elapsed();
+                eprintln!("[McpHub][INFO] Server '{}' negotiated protocol: {}", name, proc.protocol_version);
                 eprintln!("[McpHub][INFO] Server '{}' ready: {} tools in {:.0}ms", name, tools.len(), elapsed.as_secs_f64() * 1000.0);
-                first_tools = tools.clone();
+                first_tools = tools;
             }
-
-            proc.tools = tools;
-            procs.push(Arc::new(Mutex::new(proc)));
+            procs.push(proc);
         }
 
-        let pool = Arc::new(ServerPool {
-            procs,
-            next_idx: AtomicUsize::new(0),
-        });
+        let pool = Arc::new(ServerPool::new(procs, first_tools.clone(), config));
 
-        let mut pools = self.pools.lock().await;
-        pools.insert(name.to_string(), pool);
+        // Concurrent callers may have raced to start the same server: keep the first
+        let existing = {
+            let mut pools = self.pools.lock().await;
+            match pools.get(name) {
+                Some(existing) => Some(existing.clone()),
+                None => {
+                    pools.insert(name.to_string(), pool.clone());
+                    None
+                }
+            }
+        };
+        if let Some(existing) = existing {
+            for proc in &pool.instances() {
+                proc.shutdown().await;
+            }
+            return Ok(existing.tools());
+        }
 
         Ok(first_tools)
     }
 
+    /// Ask a running server for its tools again and remember the new list.
+    /// Re-list the tools of `name` on its shared pool and on every session's
+    /// private pool; the notification does not say which instance sent it.
+    pub async fn refresh_tools(&self, name: &str) -> Result<Vec<ToolDef>, String> {
+        let mut pools: Vec<Arc<ServerPool>> = self.pools.lock().await.get(name).cloned().into_iter().collect();
+        pools.extend(
+            self.sessions.lock().await
+                .iter()
+                .filter(|((server, _), _)| server == name)
+                .map(|(_, pool)| pool.clone()),
+        );
+        if pools.is_empty() {
+            return Err(format!("Server not running: {}", name));
+        }
+
+        let mut listed: Result<Vec<ToolDef>, String> = Err(format!("Server not running: {}", name));
+        for pool in pools {
+            match send_request(&pool.pick(None), "tools/list", serde_json::json!({}), None).await {
+                Ok(result) => {
+                    let tools = parse_tools(&result);
+                    if let Ok(mut current) = pool.tools.lock() {
+                        *current = tools.clone();
+                    }
+                    if listed.is_err() {
+                        listed = Ok(tools);
+                    }
+                }
+                Err(e) if listed.is_err() => listed = Err(e),
+                Err(_) => {}
+            }
+        }
+        self.results.invalidate(name);
+        listed
+    }
+
+    async fn get_pool(&self, server_name: &str) -> Result<Arc<ServerPool>, String> {
+        let pools = self.pools.lock().await;
+        pools.get(server_name).cloned().ok_or_else(|| format!("Server not running: {}", server_name))
+    }
+
     pub async fn call_method(
         &self,
         server_name: &str,
         method: &str,
         arguments: serde_json::Value,
+        caller: Option<&Caller>,
     ) -> Result<serde_json::Value, String> {
         let resolved = self.resolve_name(server_name).await
             .ok_or_else(|| format!("Unknown server: {}", server_name))?;
         let server_name = resolved.as_str();
 
-        if !self.is_running(server_name).await {
+        if !self.is_session_scoped(server_name, caller).await && !self.is_running(server_name).await {
             return Err(format!("Server not running: {}", server_name));
         }
 
-        let pool = {
-            let pools = self.pools.lock().await;
-            pools.get(server_name).cloned().ok_or_else(|| format!("Server not running: {}", server_name))?
-        };
+        let (breaker, throttle) = self.admission_config(server_name).await;
+        let _permits = self.throttles.acquire(server_name, None, &throttle).await?;
+        let attempt = self.breakers.admit(server_name, &breaker)?;
+        let result = self.request_with_retry(server_name, method, arguments, caller).await;
+        attempt.record(result.as_ref().err().map(String::as_str).filter(|e| is_server_failure(e)));
+        result
+    }
 
-        let idx = pool.next_idx.fetch_add(1, Ordering::Relaxed) % pool.procs.len();
-        let result = {
-            let mut proc = pool.procs[idx].lock().await;
-            proc.last_used = Instant::now();
-            send_request(&mut proc, method, arguments.clone()).await
+    pub async fn call_tool(
+        &self,
+        server_name: &str,
+        tool_name: &str,
+        arguments: serde_json::Value,
+        caller: Option<&Caller>,
+        progress_token: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value, String> {
+        let resolved = self.resolve_name(server_name).await
+            .ok_or_else(|| format!("Unknown server: {}", server_name))?;
+        let server_name = resolved.as_str();
+
+        // Results from the shared pool may serve other callers; a private instance's may not
+        let shared = !self.is_session_scoped(server_name, caller).await;
+        let key = shared.then(|| result_cache::key(server_name, tool_name, &arguments));
+        let caching = match shared {
+            true => self.configs.lock().await.get(server_name).map(|c| c.cache.clone()).filter(|c| c.enabled),
+            false => None,
         };
 
-        match result {
-            Err(e) if is_connection_error(&e) => {
-                eprintln!("[McpHub][WARN] Connection error on '{}': {}. Retrying...", server_name, e);
-                self.restart_server(server_name).await?;
-                
-                let pool = {
-                    let pools = self.pools.lock().await;
-                    pools.get(server_name).cloned().ok_or_else(|| format!("Server not running: {}", server_name))?
-                };
+        // Unknown until the server has listed its tools
+        let read_only = self.is_read_only(server_name, tool_name).await;
+        let cacheable = |c: &crate::config::Caching| read_only.is_none_or(|ro| c.ttl(tool_name, ro).is_some());
 
-                let idx = pool.next_idx.fetch_add(1, Ordering::Relaxed) % pool.procs.len();
-                let mut proc = pool.procs[idx].lock().await;
-                proc.last_used = Instant::now();
-                send_request(&mut proc, method, arguments).await
+        // Repeats of a read-only call within its TTL never reach the server
+        if let (true, Some(key)) = (caching.as_ref().is_some_and(cacheable), &key) {
+            if let Some(hit) = self.results.get(server_name, key) {
+                return Ok(hit);
             }
-            other => other,
+        }
+
+        // Identical read-only calls in flight at the same time share one upstream request
+        let coalesce = shared && read_only == Some(true);
+        loop {
+            let leader = match key.as_deref().filter(|_| coalesce).map(|k| self.flights.join(server_name, k)) {
+                Some(Flight::Follower(rx)) => match coalesce::wait(rx).await {
+                    Some(result) => return result,
+                    None => continue,
+                },
+                Some(Flight::Leader(leader)) => Some(leader),
+                None => None,
+            };
+
+            let result = self.send_tool_call(server_name, tool_name, arguments, caller, progress_token).await;
+            if let (Ok(value), Some(caching), Some(key)) = (&result, caching, key) {
+                let read_only = self.is_read_only(server_name, tool_name).await == Some(true);
+                match caching.ttl(tool_name, read_only) {
+                    Some(ttl) if value.get("isError") != Some(&serde_json::Value::Bool(true)) => {
+                        self.results.insert(server_name, key, value, ttl);
+                    }
+                    Some(_) => {}
+                    // A tool that may write makes what was read stale
+                    None => {
+                        self.results.invalidate(server_name);
+                    }
+                }
+            }
+            if let Some(leader) = leader {
+                leader.finish(&result);
+            }
+            return result;
         }
     }
 
-    pub async fn call_tool(
+    async fn send_tool_call(
         &self,
         server_name: &str,
         tool_name: &str,
         arguments: serde_json::Value,
+        caller: Option<&Caller>,
+        progress_token: Option<serde_json::Value>,
     ) -> Result<serde_json::Value, String> {
-        let resolved = self.resolve_name(server_name).await
-            .ok_or_else(|| format!("Unknown server: {}", server_name))?;
-        let server_name = resolved.as_str();
+        // Queue for a slot first; then an open circuit fails fast, before the start retry ladder
+        let (breaker, throttle) = self.admission_config(server_name).await;
+        let _permits = self.throttles.acquire(server_name, Some(tool_name), &throttle).await?;
+        let attempt = self.breakers.admit(server_name, &breaker)?;
+        let result = async {
+            if !self.is_session_scoped(server_name, caller).await && !self.is_running(server_name).await {
+                self.start_server(server_name).await?;
+            }
 
-        if !self.is_running(server_name).await {
-            self.start_server(server_name).await?;
-        }
+            let mut params = serde_json::json!({ "name": tool_name, "arguments": arguments });
+            if let Some(token) = progress_token {
+                params["_meta"] = serde_json::json!({ "progressToken": token });
+            }
+            self.request_with_retry(server_name, "tools/call", params, caller).await
+        }.await;
+        attempt.record(result.as_ref().err().map(String::as_str).filter(|e| is_server_failure(e)));
+        result
+    }
 
-        let pool = {
-            let pools = self.pools.lock().await;
-            pools.get(server_name).cloned().ok_or_else(|| format!("Server not running: {}", server_name))?
-        };
+    /// Whether the running server marks `tool_name` with `readOnlyHint`; None while it is not running.
+    async fn is_read_only(&self, server_name: &str, tool_name: &str) -> Option<bool> {
+        let pool = self.get_pool(server_name).await.ok()?;
+        Some(pool.tools().iter().any(|t| {
+            t.name == tool_name
+                && t.annotations.as_ref().and_then(|a| a.get("readOnlyHint")).and_then(|v| v.as_bool()) == Some(true)
+        }))
+    }
 
-        let idx = pool.next_idx.fetch_add(1, Ordering::Relaxed) % pool.procs.len();
-        let result = {
-            let mut proc = pool.procs[idx].lock().await;
-            proc.last_used = Instant::now();
-            send_request(
-                &mut proc,
-                "tools/call",
-                serde_json::json!({ "name": tool_name, "arguments": arguments.clone() }),
-            ).await
-        };
+    /// Drop the cached results of `server_name`. Returns how many there were.
+    pub fn invalidate_cache(&self, server_name: &str) -> usize {
+        self.results.invalidate(server_name)
+    }
+
+    /// Calls per server that were served by an identical call's request.
+    pub fn coalesced_stats(&self) -> Vec<(String, u64)> {
+        self.flights.stats()
+    }
+
+    /// Cache hits, misses and live entries per server.
+    pub fn cache_stats(&self) -> Vec<(String, result_cache::Stats)> {
+        self.results.stats()
+    }
+
+    async fn admission_config(&self, server_name: &str) -> (CircuitBreaker, Throttle) {
+        let configs = self.configs.lock().await;
+        let config = configs.get(server_name);
+        (
+            config.map(|c| c.circuit_breaker.clone()).unwrap_or_default(),
+            config.map(|c| c.throttle.clone()).unwrap_or_default(),
+        )
+    }
+
+    /// Servers whose circuit breaker is open or half-open.
+    pub fn breaker_statuses(&self) -> Vec<(String, breaker::Status)> {
+        self.breakers.statuses()
+    }
+
+    /// Queue length and throttle counts per server.
+    pub fn throttle_stats(&self) -> Vec<(String, throttle::Stats)> {
+        self.throttles.stats()
+    }
+
+    /// Send to the instance the pool routes `caller` to. On a connection error restart the server
+    /// once and retry; concurrent failures on the same pool share one restart.
+    async fn request_with_retry(
+        &self,
+        server_name: &str,
+        method: &str,
+        params: serde_json::Value,
+        caller: Option<&Caller>,
+    ) -> Result<serde_json::Value, String> {
+        let pool = self.pool_for(server_name, caller).await?;
+        self.scale_up_if_saturated(server_name, &pool);
+        let proc = pool.pick(caller.map(|c| &c.peer));
+        let result = self.send_tracked(server_name, &proc, method, params.clone(), caller).await;
 
         match result {
             Err(e) if is_connection_error(&e) => {
+                // Killed for exceeding a limit: bring the server back for later
+                // calls, but don't replay this one, it would likely do it again
+                if let Some(reason) = proc.exit_reason().await.filter(|r| limits::is_exceeded(r)) {
+                    if let Err(e) = self.replace_pool(server_name, &pool, caller).await {
+                        eprintln!("[McpHub][WARN] Could not restart '{}': {}", server_name, e);
+                    }
+                    return Err(reason);
+                }
                 eprintln!("[McpHub][WARN] Connection error on '{}': {}. Retrying...", server_name, e);
-                self.restart_server(server_name).await?;
-                
-                let pool = {
-                    let pools = self.pools.lock().await;
-                    pools.get(server_name).cloned().ok_or_else(|| format!("Server not running: {}", server_name))?
-                };
+                self.replace_pool(server_name, &pool, caller).await?;
 
-                let idx = pool.next_idx.fetch_add(1, Ordering::Relaxed) % pool.procs.len();
-                let mut proc = pool.procs[idx].lock().await;
-                proc.last_used = Instant::now();
-                send_request(
-                    &mut proc,
-                    "tools/call",
-                    serde_json::json!({ "name": tool_name, "arguments": arguments }),
-                ).await
+                let pool = self.pool_for(server_name, caller).await?;
+                self.send_tracked(server_name, &pool.pick(caller.map(|c| &c.peer)), method, params, caller).await
             }
             other => other,
         }
     }
 
+    /// Replace a failed pool: restart a shared one, or drop a private one so
+    /// the session's next call starts a fresh instance.
+    async fn replace_pool(&self, server_name: &str, failed: &Arc<ServerPool>, caller: Option<&Caller>) -> Result<(), String> {
+        if self.is_session_scoped(server_name, caller).await {
+            self.drop_session_pool(failed).await;
+            Ok(())
+        } else {
+            self.restart_pool(server_name, failed).await
+        }
+    }
+
+    /// Whether `caller` gets a private instance of `server_name`.
+    async fn is_session_scoped(&self, server_name: &str, caller: Option<&Caller>) -> bool {
+        caller.is_some()
+            && self.configs.lock().await.get(server_name).is_some_and(|c| c.scope == Scope::Session)
+    }
+
+    /// The pool serving `caller`: its private instance of a `"scope": "session"`
+    /// server, started on first use, or else the shared pool. A private
+    /// instance is stopped once the session's peer is dropped.
+    async fn pool_for(&self, server_name: &str, caller: Option<&Caller>) -> Result<Arc<ServerPool>, String> {
+        let peer = match caller {
+            Some(caller) if self.is_session_scoped(server_name, Some(caller)).await => &caller.peer,
+            _ => return self.get_pool(server_name).await,
+        };
+        let key = (server_name.to_string(), peer.id());
+        if let Some(pool) = self.sessions.lock().await.get(&key) {
+            return Ok(pool.clone());
+        }
+
+        let mut config = {
+            let configs = self.configs.lock().await;
+            configs.get(server_name).ok_or_else(|| format!("Unknown server: {}", server_name))?.clone()
+        };
+        // A private instance never grows into a pool
+        config.pool.min = 1;
+        config.pool.max = 1;
+        let stderr_mark = self.stderr.mark(server_name);
+        let (proc, tools) = match start_instance(server_name, &config, &self.stderr, &self.tools_changed).await {
+            Ok(started) => started,
+            Err(e) => {
+                let tail = self.stderr.since(server_name, stderr_mark, stderr::TAIL_LINES);
+                return Err(StderrBuffers::annotate(&e, &tail));
+            }
+        };
+        let pool = Arc::new(ServerPool::new(vec![proc], tools, config));
+
+        {
+            let mut sessions = self.sessions.lock().await;
+            if let Some(existing) = sessions.get(&key).cloned() {
+                // Another call from the same session started one first
+                drop(sessions);
+                for proc in &pool.instances() {
+                    proc.shutdown().await;
+                }
+                return Ok(existing);
+            }
+            sessions.insert(key.clone(), pool.clone());
+        }
+        eprintln!("[McpHub][INFO] Started {} for session {}", server_name, peer.id());
+
+        let mut closed = peer.closed();
+        let sessions = self.sessions.clone();
+        tokio::spawn(async move {
+            let _ = closed.changed().await;
+            let removed = sessions.lock().await.remove(&key);
+            if let Some(pool) = removed {
+                for proc in &pool.instances() {
+                    proc.shutdown().await;
+                }
+                eprintln!("[McpHub][INFO] Stopped {} for ended session {}", key.0, key.1);
+            }
+        });
+        Ok(pool)
+    }
+
+    /// Forget a failed private instance so the next call starts a fresh one.
+    async fn drop_session_pool(&self, failed: &Arc<ServerPool>) {
+        let removed = {
+            let mut sessions = self.sessions.lock().await;
+            let key = sessions.iter().find(|(_, p)| Arc::ptr_eq(p, failed)).map(|(k, _)| k.clone());
+            key.and_then(|k| sessions.remove(&k))
+        };
+        if let Some(pool) = removed {
+            for proc in &pool.instances() {
+                proc.shutdown().await;
+            }
+        }
+    }
+
+    /// Start one more instance in the background when every instance is busy.
+    /// The call that noticed still goes to an existing instance.
+    fn scale_up_if_saturated(&self, name: &str, pool: &Arc<ServerPool>) {
+        if !pool.saturated() || pool.scaling.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let name = name.to_string();
+        let pool = pool.clone();
+        let pools = self.pools.clone();
+        let stderr = self.stderr.clone();
+        let tools_changed = self.tools_changed.clone();
+        tokio::spawn(async move {
+            match start_instance(&name, &pool.config, &stderr, &tools_changed).await {
+                Ok((proc, _)) => {
+                    // Held while adding, so a concurrent stop sees the new instance
+                    let pools = pools.lock().await;
+                    if pools.get(&name).is_some_and(|p| Arc::ptr_eq(p, &pool)) {
+                        let count = pool.add(proc);
+                        eprintln!("[McpHub][INFO] Scaled up {} to {} instances", name, count);
+                    } else {
+                        drop(pools);
+                        proc.shutdown().await;
+                    }
+                }
+                Err(e) => eprintln!("[McpHub][WARN] Could not scale up {}: {}", name, e),
+            }
+            pool.scaling.store(false, Ordering::Release);
+        });
+    }
+
+    /// (server, instances, calls outstanding) for every running server,
+    /// private session instances included.
+    pub async fn pool_stats(&self) -> Vec<(String, usize, usize)> {
+        let mut stats: HashMap<String, (usize, usize)> = HashMap::new();
+        for (name, pool) in self.pools.lock().await.iter() {
+            let entry = stats.entry(name.clone()).or_default();
+            entry.0 += pool.instances().len();
+            entry.1 += pool.queue_depth();
+        }
+        for ((name, _), pool) in self.sessions.lock().await.iter() {
+            let entry = stats.entry(name.clone()).or_default();
+            entry.0 += 1;
+            entry.1 += pool.queue_depth();
+        }
+        stats.into_iter().map(|(name, (instances, depth))| (name, instances, depth)).collect()
+    }
+
+    /// Send on `proc`, recording the child's request id under the client's own,
+    /// so a cancellation from that client can find it.
+    async fn send_tracked(
+        &self,
+        server_name: &str,
+        proc: &Arc<ChildProcess>,
+        method: &str,
+        params: serde_json::Value,
+        caller: Option<&Caller>,
+    ) -> Result<serde_json::Value, String> {
+        let id = proc.next_request_id();
+        let key = caller.and_then(Caller::key);
+        if let Some(key) = &key {
+            let entry = InFlight { server: server_name.to_string(), proc: Arc::downgrade(proc), child_id: id };
+            self.in_flight.lock().await.insert(key.clone(), entry);
+        }
+
+        let timeout = std::time::Duration::from_secs(proc.timeouts.request_secs(method, &params));
+        let result = exchange(proc, id, method, params, timeout, caller).await;
+
+        if let Some(key) = key {
+            self.in_flight.lock().await.remove(&key);
+        }
+        result
+    }
+
+    /// Cancel the upstream call serving `request_id` from `peer`: the child is
+    /// told under its own request id, and the waiting call ends with a cancelled
+    /// error. Returns false if no such call is in flight.
+    pub async fn cancel_request(&self, peer: &ClientPeer, request_id: &serde_json::Value, reason: Option<&str>) -> bool {
+        let entry = match self.in_flight.lock().await.remove(&request_key(peer, request_id)) {
+            Some(entry) => entry,
+            None => return false,
+        };
+        let proc = match entry.proc.upgrade() {
+            Some(proc) => proc,
+            None => return false,
+        };
+
+        let mut params = serde_json::json!({ "requestId": entry.child_id });
+        if let Some(reason) = reason {
+            params["reason"] = serde_json::json!(reason);
+        }
+        let _ = send_notification(&proc, "notifications/cancelled", params).await;
+        proc.inbox.cancel(entry.child_id, reason.unwrap_or("request cancelled by client")).await;
+        eprintln!("[McpHub][INFO] Cancelled request {} on '{}'", request_id, entry.server);
+        true
+    }
+
+    /// Restart `name` unless another caller already replaced the failed pool.
+    async fn restart_pool(&self, name: &str, failed: &Arc<ServerPool>) -> Result<(), String> {
+        let replaced = {
+            let pools = self.pools.lock().await;
+            pools.get(name).is_some_and(|p| !Arc::ptr_eq(p, failed))
+        };
+        if replaced {
+            return Ok(());
+        }
+        self.restart_server(name).await.map(|_| ())
+    }
+
+    /// Buffered stderr of `name`, oldest line first.
+    pub fn stderr_lines(&self, name: &str) -> Vec<String> {
+        self.stderr.lines(name)
+    }
+
     pub async fn is_running(&self, name: &str) -> bool {
         let pools = self.pools.lock().await;
         pools.contains_key(name)
@@ -322,25 +1023,48 @@ impl ChildManager {
 
     #[allow(dead_code)]
     pub async fn stop_server(&self, name: &str) {
-        let mut pools = self.pools.lock().await;
-        if let Some(pool) = pools.remove(name) {
-            for proc_arc in &pool.procs {
-                let mut proc = proc_arc.lock().await;
-                let _ = proc.child.kill().await;
-            }
+        let (running, procs) = self.take_server(name).await;
+        shutdown_all(procs).await;
+        if running {
             eprintln!("[McpHub][INFO] Stopped server: {}", name);
         }
     }
 
+    /// Take the shared pool and the session pools of `name` out of the maps.
+    /// Returns whether the shared pool was running, and every instance to stop.
+    async fn take_server(&self, name: &str) -> (bool, Vec<Arc<ChildProcess>>) {
+        let shared = self.pools.lock().await.remove(name);
+        let sessions: Vec<Arc<ServerPool>> = {
+            let mut sessions = self.sessions.lock().await;
+            let keys: Vec<(String, u64)> = sessions.keys().filter(|(server, _)| server == name).cloned().collect();
+            keys.iter().filter_map(|k| sessions.remove(k)).collect()
+        };
+        let procs = shared.iter().chain(&sessions).flat_map(|pool| pool.instances()).collect();
+        (shared.is_some(), procs)
+    }
+
+    /// Stop every instance at once, so grace periods run side by side.
     pub async fn stop_all(&self) {
-        let mut pools = self.pools.lock().await;
-        for (name, pool) in pools.drain() {
-            for proc_arc in &pool.procs {
-                let mut proc = proc_arc.lock().await;
-                let _ = proc.child.kill().await;
-            }
-            eprintln!("[McpHub][INFO] Stopped server: {}", name);
+        let drained: Vec<(String, Arc<ServerPool>)> = self.pools.lock().await.drain().collect();
+        let mut stopping = tokio::task::JoinSet::new();
+        for (name, pool) in drained {
+            let procs = pool.instances();
+            stopping.spawn(async move {
+                for proc in &procs {
+                    proc.shutdown().await;
+                }
+                eprintln!("[McpHub][INFO] Stopped server: {}", name);
+            });
         }
+        let sessions: Vec<Arc<ServerPool>> = self.sessions.lock().await.drain().map(|(_, pool)| pool).collect();
+        for pool in sessions {
+            stopping.spawn(async move {
+                for proc in &pool.instances() {
+                    proc.shutdown().await;
+                }
+            });
+        }
+        while stopping.join_next().await.is_some() {}
     }
 
     pub async fn server_names(&self) -> Vec<String> {
@@ -353,62 +1077,33 @@ impl ChildManager {
         method: &str,
         params: serde_json::Value,
     ) -> Vec<(String, Result<serde_json::Value, String>)> {
-        let running_servers: Vec<String> = {
+        let running: Vec<(String, Arc<ServerPool>)> = {
             let pools = self.pools.lock().await;
-            pools.keys().cloned().collect()
+            pools.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
         };
 
         let mut results = Vec::new();
-        for name in running_servers {
-            let pool_opt = {
-                let pools = self.pools.lock().await;
-                pools.get(&name).cloned()
-            };
-            if let Some(pool) = pool_opt {
-                let idx = pool.next_idx.fetch_add(1, Ordering::Relaxed) % pool.procs.len();
-                let mut proc = pool.procs[idx].lock().await;
-                proc.last_used = Instant::now();
-                let res = send_request(&mut proc, method, params.clone()).await;
-                results.push((name, res));
-            } else {
-                results.push((name, Err("Server stopped".into())));
-            }
-        }
-        
-        results
-    }
-
-    pub async fn forward_notification(
-        &self,
-        server_name: &str,
-        method: &str,
-        params: serde_json::Value,
-    ) -> Result<(), String> {
-        let pool = {
-            let pools = self.pools.lock().await;
-            pools.get(server_name).cloned().ok_or_else(|| format!("Server not running: {}", server_name))?
-        };
-
-        // Forward to all instances in the pool to ensure it hits the right one
-        for proc_arc in &pool.procs {
-            let mut proc = proc_arc.lock().await;
-            proc.last_used = Instant::now();
-            let _ = send_notification(&mut proc, method, params.clone()).await;
+        for (name, pool) in running {
+            let res = send_request(&pool.pick(None), method, params.clone(), None).await;
+            results.push((name, res));
         }
 
-        Ok(())
+        results
     }
 
+    /// Stop idle servers and shrink grown pools. Instances are taken out of
+    /// the pool map under its lock and shut down after it is released.
     pub async fn reap_idle(&self) {
         let timeout = std::time::Duration::from_millis(self.idle_timeout_ms);
+        let mut stopping = Vec::new();
         let mut pools = self.pools.lock().await;
 
         let mut idle_servers = Vec::new();
         for (name, pool) in pools.iter() {
             let mut all_idle = true;
-            for proc_arc in &pool.procs {
-                let proc = proc_arc.lock().await;
-                if proc.last_used.elapsed() <= timeout {
+            for proc in &pool.instances() {
+                // A long-running call keeps its server alive
+                if proc.idle_for() <= timeout || proc.load() > 0 {
                     all_idle = false;
                     break;
                 }
@@ -420,73 +1115,78 @@ impl ChildManager {
 
         for name in idle_servers {
             if let Some(pool) = pools.remove(&name) {
-                for proc_arc in &pool.procs {
-                    let mut proc = proc_arc.lock().await;
-                    let _ = proc.child.kill().await;
-                }
+                stopping.extend(pool.instances());
                 eprintln!("[McpHub][INFO] Idle-stopped server: {}", name);
             }
         }
+
+        // Shrink grown pools back towards their minimum
+        for (name, pool) in pools.iter() {
+            let idle = std::time::Duration::from_secs(pool.config.pool.scale_down_idle_secs);
+            let removed = pool.remove_idle(idle);
+            if removed.is_empty() {
+                continue;
+            }
+            stopping.extend(removed);
+            eprintln!("[McpHub][INFO] Scaled down {} to {} instances", name, pool.instances().len());
+        }
+        drop(pools);
+
+        shutdown_all(stopping).await;
     }
 
     pub async fn health_check(&self) -> Vec<(String, String)> {
         let mut dead_servers: Vec<(String, String)> = Vec::new();
-        let mut pools = self.pools.lock().await;
 
-        let names: Vec<String> = pools.keys().cloned().collect();
-        for name in names {
-            let pool = match pools.get(&name) {
-                Some(p) => p.clone(),
-                None => continue,
-            };
+        // Ping from a snapshot so tool calls are not blocked behind the pool map
+        let running: Vec<(String, Arc<ServerPool>)> = {
+            let pools = self.pools.lock().await;
+            pools.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+        };
 
+        for (name, pool) in running {
             let mut pool_dead = false;
             let mut reason = String::new();
 
-            for proc_arc in &pool.procs {
-                let mut proc = proc_arc.lock().await;
-                
-                match proc.child.try_wait() {
-                    Ok(Some(status)) => {
-                        pool_dead = true;
-                        reason = format!("Process exited: {}", status);
-                        break;
-                    }
-                    Ok(None) => {} 
-                    Err(e) => {
-                        pool_dead = true;
-                        reason = format!("Process check failed: {}", e);
-                        break;
-                    }
+            for proc in &pool.instances() {
+                if let Some(exit_reason) = proc.exit_reason().await {
+                    pool_dead = true;
+                    reason = exit_reason;
+                    break;
                 }
 
-                let ping_timeout = std::time::Duration::from_secs(5);
-                let ping_result = tokio::time::timeout(
-                    ping_timeout,
-                    send_request_inner(&mut proc, "ping", serde_json::json!({})),
-                ).await;
+                let ping_secs = proc.timeouts.ping_secs();
+                let ping_timeout = std::time::Duration::from_secs(ping_secs);
+                let ping_result = send_request_timeout(proc, "ping", serde_json::json!({}), ping_timeout, None).await;
 
                 match ping_result {
-                    Ok(Ok(_)) => {} 
-                    Ok(Err(e)) => {
+                    Ok(_) => {}
+                    Err(e) if e.starts_with("Timeout") => {
                         pool_dead = true;
-                        reason = format!("Ping error: {}", e);
+                        reason = format!("Ping timeout ({}s)", ping_secs);
                         break;
                     }
-                    Err(_) => {
+                    Err(e) => {
                         pool_dead = true;
-                        reason = "Ping timeout (5s)".to_string();
+                        reason = format!("Ping error: {}", e);
                         break;
                     }
                 }
             }
 
             if pool_dead {
-                dead_servers.push((name.clone(), reason));
-                if let Some(pool) = pools.remove(&name) {
-                    for proc_arc in &pool.procs {
-                        let mut proc = proc_arc.lock().await;
-                        let _ = proc.child.kill().await;
+                let tail = self.stderr.since(&name, 0, stderr::TAIL_LINES);
+                dead_servers.push((name.clone(), StderrBuffers::annotate(&reason, &tail)));
+                let removed = {
+                    let mut pools = self.pools.lock().await;
+                    match pools.get(&name) {
+                        Some(current) if Arc::ptr_eq(current, &pool) => pools.remove(&name),
+                        _ => None,
+                    }
+                };
+                if let Some(pool) = removed {
+                    for proc in &pool.instances() {
+                        proc.shutdown().await;
                     }
                 }
             }
@@ -496,14 +1196,9 @@ impl ChildManager {
     }
 
     pub async fn restart_server(&self, name: &str) -> Result<usize, String> {
-        {
-            let mut pools = self.pools.lock().await;
-            if let Some(pool) = pools.remove(name) {
-                for proc_arc in &pool.procs {
-                    let mut proc = proc_arc.lock().await;
-                    let _ = proc.child.kill().await;
-                }
-            }
+        let removed = self.pools.lock().await.remove(name);
+        if let Some(pool) = removed {
+            shutdown_all(pool.instances()).await;
         }
         tokio::time::sleep(std::time::Duration::from_millis(500)).await;
         let tools = self.start_server(name).await?;
@@ -511,31 +1206,352 @@ impl ChildManager {
     }
 }
 
+impl ChildProcess {
+    fn touch(&self) {
+        if let Ok(mut t) = self.last_used.lock() {
+            *t = Instant::now();
+        }
+    }
+
+    fn load(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    fn next_request_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn idle_for(&self) -> std::time::Duration {
+        self.last_used.lock().map(|t| t.elapsed()).unwrap_or_default()
+    }
+
+    /// Terminate the instance: kill the process, or end the remote session.
+    async fn shutdown(&self) {
+        match &self.link {
+            Link::Stdio { child, stdin, pgid, stderr_task, .. } => {
+                stdin.lock().await.take();
+                let grace = std::time::Duration::from_secs(self.timeouts.shutdown_secs());
+                // Status checks keep answering during the grace period
+                let handle = child.lock().await.take();
+                if let Some(mut handle) = handle {
+                    stop_process_tree(self.inbox.server_name(), &mut handle, *pgid, grace).await;
+                }
+                // Let the last lines (often the reason it died) reach the buffer
+                if let Some(task) = stderr_task.lock().await.take() {
+                    let _ = tokio::time::timeout(std::time::Duration::from_millis(200), task).await;
+                }
+            }
+            Link::StreamableHttp(client) => client.close().await,
+            Link::Sse(client) => client.close(),
+        }
+        self.inbox.close().await;
+    }
+
+    /// Why the instance is gone, if it is.
+    async fn exit_reason(&self) -> Option<String> {
+        if let Link::Stdio { child, limit_exceeded, .. } = &self.link {
+            if let Some(reason) = limit_exceeded.lock().ok().and_then(|r| r.clone()) {
+                return Some(reason);
+            }
+            match child.lock().await.as_mut().map(|c| c.try_wait()) {
+                Some(Ok(Some(status))) => return Some(format!("Process exited: {}", status)),
+                Some(Ok(None)) => {}
+                Some(Err(e)) => return Some(format!("Process check failed: {}", e)),
+                None => return Some("Process stopped".into()),
+            }
+        }
+        if self.inbox.is_closed() {
+            return Some(match self.link {
+                Link::Sse(_) => "SSE stream closed".into(),
+                _ => "Server closed connection".into(),
+            });
+        }
+        None
+    }
+
+    /// Write one JSON-RPC message to the transport.
+    async fn send(&self, message: &serde_json::Value) -> Result<(), String> {
+        if self.inbox.is_closed() {
+            return Err("Server closed connection".into());
+        }
+        match &self.link {
+            Link::Stdio { stdin, .. } => {
+                let mut msg = serde_json::to_string(message).map_err(|e| e.to_string())?;
+                msg.push('\n');
+
+                let mut stdin = stdin.lock().await;
+                let stdin = stdin.as_mut().ok_or("Server closed connection")?;
+                stdin
+                    .write_all(msg.as_bytes())
+                    .await
+                    .map_err(|e| format!("Write error: {}", e))?;
+                stdin
+                    .flush()
+                    .await
+                    .map_err(|e| format!("Flush error: {}", e))
+            }
+            Link::StreamableHttp(client) => client.send(message, &self.inbox).await,
+            Link::Sse(client) => client.send(message).await,
+        }
+    }
+}
+
+/// Start one instance of `name`: open the transport, negotiate, list tools.
+async fn start_instance(
+    name: &str,
+    config: &ServerConfig,
+    stderr: &Arc<StderrBuffers>,
+    tools_changed: &mpsc::UnboundedSender<String>,
+) -> Result<(Arc<ChildProcess>, Vec<ToolDef>), String> {
+    let (inbox, server_requests) = Inbox::new(name, tools_changed.clone());
+    let link = open_link(name, config, &inbox, stderr).await?;
+
+    let mut proc = ChildProcess {
+        link,
+        inbox,
+        next_id: AtomicU64::new(1),
+        last_used: std::sync::Mutex::new(Instant::now()),
+        protocol_version: LATEST_PROTOCOL_VERSION,
+        timeouts: config.timeouts.clone(),
+        active: AtomicUsize::new(0),
+    };
+    let startup_timeout = std::time::Duration::from_secs(proc.timeouts.startup_secs());
+
+    proc.protocol_version = match initialize(&proc, startup_timeout).await {
+        Ok(version) => version,
+        Err(e) => {
+            proc.shutdown().await;
+            return Err(e);
+        }
+    };
+    if let Link::StreamableHttp(client) = &proc.link {
+        client.set_protocol_version(proc.protocol_version);
+    }
+
+    let listed = match send_notification(&proc, "notifications/initialized", serde_json::json!({})).await {
+        Ok(()) => send_request_timeout(&proc, "tools/list", serde_json::json!({}), startup_timeout, None).await,
+        Err(e) => Err(e),
+    };
+    let tools = match listed {
+        Ok(result) => parse_tools(&result),
+        Err(e) => {
+            proc.shutdown().await;
+            return Err(e);
+        }
+    };
+
+    let proc = Arc::new(proc);
+    tokio::spawn(relay_server_requests(Arc::downgrade(&proc), server_requests));
+    Ok((proc, tools))
+}
+
+/// The command line of a stdio server with its `cwd` and environment applied.
+/// Also used by the dashboard's repair check, so both start it the same way.
+pub fn stdio_command(config: &ServerConfig) -> Result<Command, String> {
+    let mut cmd = Command::new(&config.command);
+    cmd.args(&config.args).env_clear().envs(config.environment()?);
+    if let Some(cwd) = &config.launch.cwd {
+        if !std::path::Path::new(cwd).is_dir() {
+            return Err(format!("Working directory {} does not exist", cwd));
+        }
+        cmd.current_dir(cwd);
+    }
+    Ok(cmd)
+}
+
+/// Open the transport for one instance of `name` according to its config.
+/// Incoming messages are delivered to `inbox`.
+async fn open_link(
+    name: &str,
+    config: &ServerConfig,
+    inbox: &Inbox,
+    stderr: &Arc<StderrBuffers>,
+) -> Result<Link, String> {
+    match &config.transport {
+        Transport::Stdio => {
+            let mut cmd = stdio_command(config)?;
+            cmd.stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped());
+            #[cfg(unix)]
+            cmd.process_group(0);
+            limits::apply_rlimits(&mut cmd, &config.limits);
+            if let Some(sandbox) = &config.sandbox {
+                sandbox::apply(name, &mut cmd, sandbox).map_err(|e| format!("Cannot sandbox {}: {}", name, e))?;
+            }
+
+            let mut child = cmd.spawn().map_err(|e| format!("Failed to spawn {}: {}", name, e))?;
+            let pgid = if cfg!(unix) { child.id() } else { None };
+            if let Some(pgid) = pgid {
+                procgroup::record(pgid, name).await;
+            }
+            let limit_exceeded = Arc::new(std::sync::Mutex::new(None));
+            let enforced = cfg!(target_os = "linux") && pgid.is_some();
+            if !enforced && config.limits != Limits::default() {
+                eprintln!("[McpHub][WARN] {}: resource limits are only enforced on Linux", name);
+            }
+            if let Some(pgid) = pgid.filter(|_| enforced && config.limits.watched()) {
+                tokio::spawn(watch_limits(name.to_string(), pgid, config.limits.clone(), limit_exceeded.clone()));
+            }
+            let stdin = child.stdin.take().ok_or("No stdin")?;
+            let stdout = child.stdout.take().ok_or("No stdout")?;
+            let child_stderr = child.stderr.take().ok_or("No stderr")?;
+
+            tokio::spawn(read_stdout(BufReader::new(stdout), inbox.clone()));
+            let stderr_task = tokio::spawn(read_stderr(BufReader::new(child_stderr), name.to_string(), stderr.clone()));
+            Ok(Link::Stdio {
+                child: Box::new(Mutex::new(Some(child))),
+                stdin: Mutex::new(Some(stdin)),
+                pgid,
+                limit_exceeded,
+                stderr_task: Mutex::new(Some(stderr_task)),
+            })
+        }
+        Transport::StreamableHttp { url, headers } => {
+            let headers = secrets::resolve_map(headers)?;
+            Ok(Link::StreamableHttp(StreamableHttpClient::new(url, &headers)?))
+        }
+        Transport::Sse { url, headers } => {
+            let headers = secrets::resolve_map(headers)?;
+            Ok(Link::Sse(LegacySseClient::connect(url, &headers, inbox.clone()).await?))
+        }
+    }
+}
+
+/// Stop a stdio child and everything in its process group: SIGTERM, up to
+/// `grace` to exit, then SIGKILL. The child is reaped either way.
+async fn stop_process_tree(name: &str, child: &mut Child, pgid: Option<u32>, grace: std::time::Duration) {
+    let pgid = match pgid {
+        Some(pgid) => pgid,
+        None => {
+            let _ = child.kill().await;
+            return;
+        }
+    };
+
+    procgroup::signal(pgid, procgroup::Signal::Term);
+    let deadline = Instant::now() + grace;
+    loop {
+        // Reap the leader as soon as it exits, or the group never looks empty
+        let _ = child.try_wait();
+        if !procgroup::alive(pgid) || Instant::now() >= deadline {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    if procgroup::alive(pgid) {
+        eprintln!("[McpHub][WARN] {} did not exit within {}s of SIGTERM; killing its process group", name, grace.as_secs());
+        procgroup::signal(pgid, procgroup::Signal::Kill);
+    }
+    let _ = child.kill().await;
+    procgroup::forget(pgid).await;
+}
+
+/// Sample a limited server's process group until it is gone, and kill the
+/// whole group once it exceeds a limit. The reason is left in `exceeded`.
+async fn watch_limits(name: String, pgid: u32, limits: Limits, exceeded: Arc<std::sync::Mutex<Option<String>>>) {
+    let started = Instant::now();
+    loop {
+        tokio::time::sleep(limits::SAMPLE_INTERVAL).await;
+        let usage = match limits::group_usage(pgid) {
+            Some(usage) => usage,
+            None => return,
+        };
+        if let Some(reason) = limits::exceeded(&limits, &usage, started.elapsed()) {
+            eprintln!("[McpHub][WARN] {}: {}; killing its process group", name, reason);
+            if let Ok(mut slot) = exceeded.lock() {
+                *slot = Some(reason.clone());
+            }
+            procgroup::signal(pgid, procgroup::Signal::Kill);
+            // The schema cache is a file read and rewritten in full
+            let _ = tokio::task::spawn_blocking(move || crate::cache::note_server_error(&name, &reason)).await;
+            return;
+        }
+    }
+}
+
+/// Reader task for a stdio child: one JSON-RPC message per line until EOF.
+async fn read_stdout(reader: BufReader<tokio::process::ChildStdout>, inbox: Inbox) {
+    let mut lines = reader.lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(line) {
+            inbox.dispatch(parsed, None).await;
+        }
+    }
+    inbox.close().await;
+}
+
+/// Stderr reader for a stdio child: every line goes to the server's ring buffer.
+async fn read_stderr(reader: BufReader<tokio::process::ChildStderr>, server_name: String, buffers: Arc<StderrBuffers>) {
+    let mut lines = reader.lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        buffers.push(&server_name, &line);
+    }
+}
+
 fn is_connection_error(e: &str) -> bool {
     e.contains("Write error") || e.contains("Flush error") || e.contains("Read error") || e.contains("Server closed connection")
 }
 
-const REQUEST_TIMEOUT_SECS: u64 = 30;
+/// A failure that says the server is unhealthy: it could not be started or
+/// reached, or did not answer in time. Errors the server itself returned and
+/// cancellations don't count against it.
+fn is_server_failure(e: &str) -> bool {
+    !is_cancelled(e) && !e.starts_with("MCP error: ") && !e.starts_with("Unknown server: ")
+}
+
+/// Shut instances down side by side, so their grace periods overlap.
+async fn shutdown_all(procs: Vec<Arc<ChildProcess>>) {
+    let mut stopping = tokio::task::JoinSet::new();
+    for proc in procs {
+        stopping.spawn(async move { proc.shutdown().await });
+    }
+    while stopping.join_next().await.is_some() {}
+}
+
+/// The call was cancelled by its client (see `ChildManager::cancel_request`).
+pub fn is_cancelled(e: &str) -> bool {
+    e.starts_with("Cancelled: ")
+}
 
+/// Send with the deadline the server's config gives `method` (and tool, for `tools/call`).
 async fn send_request(
-    proc: &mut ChildProcess,
+    proc: &ChildProcess,
     method: &str,
     params: serde_json::Value,
+    caller: Option<&Caller>,
 ) -> Result<serde_json::Value, String> {
-    let timeout = std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS);
-    match tokio::time::timeout(timeout, send_request_inner(proc, method, params)).await {
-        Ok(result) => result,
-        Err(_) => Err(format!("Timeout: server did not respond within {}s", REQUEST_TIMEOUT_SECS)),
-    }
+    let timeout = std::time::Duration::from_secs(proc.timeouts.request_secs(method, &params));
+    send_request_timeout(proc, method, params, timeout, caller).await
 }
 
-async fn send_request_inner(
-    proc: &mut ChildProcess,
+async fn send_request_timeout(
+    proc: &ChildProcess,
     method: &str,
     params: serde_json::Value,
+    timeout: std::time::Duration,
+    caller: Option<&Caller>,
 ) -> Result<serde_json::Value, String> {
-    let id = proc.next_id;
-    proc.next_id += 1;
+    exchange(proc, proc.next_request_id(), method, params, timeout, caller).await
+}
+
+/// Register a pending slot for `id`, write the request, and wait for the reader
+/// to hand back the response. The transport is only held for the write itself.
+/// On timeout the child is told to stop working on the request.
+async fn exchange(
+    proc: &ChildProcess,
+    id: u64,
+    method: &str,
+    mut params: serde_json::Value,
+    timeout: std::time::Duration,
+    caller: Option<&Caller>,
+) -> Result<serde_json::Value, String> {
+    let _active = ActiveCall::new(&proc.active);
+    let progress_token = rewrite_progress_token(&mut params, id, caller.is_some());
 
     let request = serde_json::json!({
         "jsonrpc": "2.0",
@@ -544,64 +1560,146 @@ async fn send_request_inner(
         "params": params,
     });
 
-    let mut msg = serde_json::to_string(&request).map_err(|e| e.to_string())?;
-    msg.push('\n');
-
-    proc.stdin
-        .write_all(msg.as_bytes())
-        .await
-        .map_err(|e| format!("Write error: {}", e))?;
-    proc.stdin
-        .flush()
-        .await
-        .map_err(|e| format!("Flush error: {}", e))?;
+    let (tx, rx) = oneshot::channel();
+    proc.inbox.register(id, tx, caller, progress_token).await;
 
-    let mut lines = proc.stdout_lines.lock().await;
-    loop {
-        let line = lines
-            .next_line()
-            .await
-            .map_err(|e| format!("Read error: {}", e))?
-            .ok_or("Server closed connection")?;
+    let round_trip = async {
+        proc.send(&request).await?;
+        rx.await.map_err(|_| "Server closed connection".to_string())?
+    };
 
-        let line = line.trim().to_string();
-        if line.is_empty() {
-            continue;
+    match tokio::time::timeout(timeout, round_trip).await {
+        Ok(Ok(response)) => response_result(&response),
+        Ok(Err(e)) => {
+            proc.inbox.forget(id).await;
+            Err(e)
+        }
+        Err(_) => {
+            proc.inbox.forget(id).await;
+            // `initialize` must not be cancelled; the instance is shut down instead
+            if method != "initialize" {
+                let reason = format!("McpHub timed out after {}s", timeout.as_secs());
+                let _ = send_notification(proc, "notifications/cancelled", serde_json::json!({ "requestId": id, "reason": reason })).await;
+            }
+            Err(format!("Timeout: server did not respond within {}s", timeout.as_secs()))
         }
+    }
+}
 
-        let parsed: serde_json::Value = match serde_json::from_str(&line) {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
+/// Swap the client's `_meta.progressToken` for our request id, which is unique on
+/// this connection even when several clients share a pooled instance.
+/// Returns the client's token. Without a client to report to, the token is dropped.
+fn rewrite_progress_token(params: &mut serde_json::Value, id: u64, has_client: bool) -> Option<serde_json::Value> {
+    let meta = params.get_mut("_meta")?.as_object_mut()?;
+    let token = meta.remove("progressToken")?;
+    if has_client {
+        meta.insert("progressToken".into(), serde_json::json!(id));
+        return Some(token);
+    }
+    if meta.is_empty() {
+        params.as_object_mut()?.remove("_meta");
+    }
+    None
+}
 
-        if parsed.get("id").is_none() {
-            if let Some(method) = parsed.get("method").and_then(|v| v.as_str()) {
-                if method == "notifications/message" {
-                    if let Some(params) = parsed.get("params") {
-                        if let Some(level) = params.get("level").and_then(|v| v.as_str()) {
-                            if let Some(data) = params.get("data").and_then(|v| v.as_str()) {
-                                eprintln!("[McpHub][{}][{}] {}", proc.server_name, level.to_uppercase(), data);
-                            }
-                        }
-                    }
-                }
+/// Offer our newest protocol version, stepping down to older ones while the
+/// server rejects `initialize` outright. Returns the version the server chose.
+async fn initialize(proc: &ChildProcess, timeout: std::time::Duration) -> Result<&'static str, String> {
+    let mut last_error = String::new();
+    for offered in SUPPORTED_PROTOCOL_VERSIONS {
+        // Advertise what we can relay to downstream clients
+        let mut capabilities = serde_json::json!({ "sampling": {}, "roots": {} });
+        if offered >= "2025-06-18" {
+            capabilities["elicitation"] = serde_json::json!({});
+        }
+        let params = serde_json::json!({
+            "protocolVersion": offered,
+            "capabilities": capabilities,
+            "clientInfo": { "name": "McpHub", "version": env!("CARGO_PKG_VERSION") }
+        });
+
+        match send_request_timeout(proc, "initialize", params, timeout, None).await {
+            Ok(result) => {
+                let chosen = result.get("protocolVersion").and_then(|v| v.as_str()).unwrap_or(offered);
+                return SUPPORTED_PROTOCOL_VERSIONS
+                    .iter()
+                    .find(|v| **v == chosen)
+                    .copied()
+                    .ok_or_else(|| format!("Unsupported protocol version: {}", chosen));
             }
-            continue;
+            Err(e) if e.starts_with("MCP error") => last_error = e,
+            Err(e) => return Err(e),
         }
+    }
+    Err(last_error)
+}
 
-        if let Some(resp_id) = parsed.get("id") {
-            if resp_id.as_u64() == Some(id) {
-                if let Some(error) = parsed.get("error") {
-                    return Err(format!("MCP error: {}", error));
+/// Relay requests from an upstream (sampling, roots, elicitation) to the client
+/// behind the originating call, and write the answer back under the upstream's id.
+/// Each request runs on its own task so a slow human does not block the others.
+async fn relay_server_requests(proc: Weak<ChildProcess>, mut requests: mpsc::UnboundedReceiver<ServerRequest>) {
+    while let Some((request, caller)) = requests.recv().await {
+        let proc = proc.clone();
+        tokio::spawn(async move {
+            let method = request.get("method").and_then(|v| v.as_str()).unwrap_or("").to_string();
+            let params = request.get("params").cloned().unwrap_or(serde_json::json!({}));
+
+            let answer = match caller {
+                Ok(caller) => caller.peer.request(caller.request_id.as_ref(), &method, params).await,
+                Err(e) => Err(e),
+            };
+
+            let mut reply = serde_json::json!({ "jsonrpc": "2.0", "id": request["id"].clone() });
+            match answer {
+                Ok(response) => match response.get("error") {
+                    Some(error) => reply["error"] = error.clone(),
+                    None => reply["result"] = response.get("result").cloned().unwrap_or(serde_json::json!({})),
+                },
+                Err(e) => {
+                    eprintln!("[McpHub][WARN] Could not relay {} from upstream: {}", method, e);
+                    reply["error"] = serde_json::json!({ "code": -32603, "message": e });
                 }
-                return Ok(parsed.get("result").cloned().unwrap_or(serde_json::Value::Null));
+            }
+
+            if let Some(proc) = proc.upgrade() {
+                let _ = proc.send(&reply).await;
+            }
+        });
+    }
+}
+
+/// The tools in a `tools/list` result.
+fn parse_tools(result: &serde_json::Value) -> Vec<ToolDef> {
+    result
+        .get("tools")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default()
+}
+
+/// Turn a JSON-RPC response message into the call result.
+fn response_result(parsed: &serde_json::Value) -> Result<serde_json::Value, String> {
+    if let Some(error) = parsed.get("error") {
+        return Err(format!("MCP error: {}", error));
+    }
+    Ok(parsed.get("result").cloned().unwrap_or(serde_json::Value::Null))
+}
+
+/// Surface `notifications/message` log lines from an upstream on our stderr.
+pub fn log_child_message(server_name: &str, parsed: &serde_json::Value) {
+    if parsed.get("method").and_then(|v| v.as_str()) != Some("notifications/message") {
+        return;
+    }
+    if let Some(params) = parsed.get("params") {
+        if let Some(level) = params.get("level").and_then(|v| v.as_str()) {
+            if let Some(data) = params.get("data").and_then(|v| v.as_str()) {
+                eprintln!("[McpHub][{}][{}] {}", server_name, level.to_uppercase(), data);
             }
         }
     }
 }
 
 async fn send_notification(
-    proc: &mut ChildProcess,
+    proc: &ChildProcess,
     method: &str,
     params: serde_json::Value,
 ) -> Result<(), String> {
@@ -611,17 +1709,147 @@ async fn send_notification(
         "params": params,
     });
 
-    let mut msg = serde_json::to_string(&notification).map_err(|e| e.to_string())?;
-    msg.push('\n');
+    proc.send(&notification).await
+}
 
-    proc.stdin
-        .write_all(msg.as_bytes())
-        .await
-        .map_err(|e| format!("Write error: {}", e))?;
-    proc.stdin
-        .flush()
-        .await
-        .map_err(|e| format!("Flush error: {}", e))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Holds the first `tools/call` until a second arrives, then asks for a
+    /// sample outside either call and answers both with what came back.
+    const UPSTREAM: &str = r#"while read -r line; do
+  id=$(echo "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  case "$line" in
+    *'"method":"initialize"'*) echo '{"jsonrpc":"2.0","id":'$id',"result":{"protocolVersion":"2025-06-18","capabilities":{"tools":{}},"serverInfo":{"name":"t","version":"1"}}}' ;;
+    *'"method":"tools/list"'*) echo '{"jsonrpc":"2.0","id":'$id',"result":{"tools":[{"name":"work","inputSchema":{"type":"object"}}]}}' ;;
+    *'"method":"tools/call"'*)
+      if [ -z "$first" ]; then first=$id; else
+        second=$id
+        echo '{"jsonrpc":"2.0","id":900,"method":"sampling/createMessage","params":{"messages":[],"maxTokens":1}}'
+      fi ;;
+    *'"id":900'*)
+      text=$(echo "$line" | sed -n 's/.*"message":"\([^"]*\)".*/\1/p')
+      for call in $first $second; do
+        echo '{"jsonrpc":"2.0","id":'$call',"result":{"content":[{"type":"text","text":"'"$text"'"}]}}'
+      done ;;
+  esac
+done"#;
+
+    #[tokio::test]
+    async fn test_server_request_with_two_callers_is_not_routed() {
+        let server = crate::config::parse_server("t", &serde_json::json!({ "command": "sh", "args": ["-c", UPSTREAM] })).unwrap();
+        let manager = ChildManager::new(HashMap::from([("t".to_string(), server)]), 60_000);
+        manager.start_server("t").await.unwrap();
+
+        let callers: Vec<Caller> = (1..=2)
+            .map(|n| Caller { peer: Arc::new(ClientPeer::sse(None)), request_id: Some(serde_json::json!(n)) })
+            .collect();
+        let (a, b) = tokio::join!(
+            manager.call_tool("t", "work", serde_json::json!({}), Some(&callers[0]), None),
+            manager.call_tool("t", "work", serde_json::json!({}), Some(&callers[1]), None),
+        );
+        for result in [a, b] {
+            assert_eq!(result.unwrap()["content"][0]["text"], "cannot route server request");
+        }
+        manager.stop_all().await;
+    }
 
-    Ok(())
-}
\ No newline at end of file
+    /// An instance with `load` calls outstanding, last used `idle` ago. Its
+    /// link is never used, so a remote one that does not connect will do.
+    fn instance(load: usize, idle: std::time::Duration) -> Arc<ChildProcess> {
+        let (inbox, _) = Inbox::new("t", mpsc::unbounded_channel().0);
+        Arc::new(ChildProcess {
+            link: Link::StreamableHttp(StreamableHttpClient::new("http://127.0.0.1:9/mcp", &HashMap::new()).unwrap()),
+            inbox,
+            next_id: AtomicU64::new(1),
+            last_used: std::sync::Mutex::new(Instant::now() - idle),
+            protocol_version: LATEST_PROTOCOL_VERSION,
+            timeouts: Timeouts::default(),
+            active: AtomicUsize::new(load),
+        })
+    }
+
+    fn pool(config: serde_json::Value, loads: &[usize]) -> ServerPool {
+        let mut config = config;
+        config["command"] = serde_json::json!("true");
+        let config = crate::config::parse_server("t", &config).unwrap();
+        let procs = loads.iter().map(|&load| instance(load, std::time::Duration::ZERO)).collect();
+        ServerPool::new(procs, Vec::new(), config)
+    }
+
+    /// Position of `proc` in the pool.
+    fn index(pool: &ServerPool, proc: &Arc<ChildProcess>) -> usize {
+        pool.instances().iter().position(|p| Arc::ptr_eq(p, proc)).unwrap()
+    }
+
+    #[test]
+    fn test_round_robin_routing() {
+        let pool = pool(serde_json::json!({ "pool": 3 }), &[5, 0, 0]);
+        let picks: Vec<usize> = (0..6).map(|_| index(&pool, &pool.pick(None))).collect();
+        assert_eq!(picks, [0, 1, 2, 0, 1, 2]);
+    }
+
+    #[test]
+    fn test_least_busy_routing() {
+        let idle = pool(serde_json::json!({ "pool": 3, "routing": "least_busy" }), &[2, 0, 1]);
+        assert!((0..3).all(|_| index(&idle, &idle.pick(None)) == 1));
+
+        // Ties go to the first candidate after the cursor, so they take turns
+        let tied = pool(serde_json::json!({ "pool": 3, "routing": "least_busy" }), &[2, 1, 1]);
+        let picks: Vec<usize> = (0..3).map(|_| index(&tied, &tied.pick(None))).collect();
+        assert_eq!(picks, [1, 1, 2]);
+    }
+
+    #[test]
+    fn test_sticky_routing() {
+        let pool = pool(serde_json::json!({ "pool": 2, "routing": "sticky" }), &[0, 0]);
+        let (a, b) = (Arc::new(ClientPeer::sse(None)), Arc::new(ClientPeer::sse(None)));
+        let home = index(&pool, &pool.pick(Some(&a)));
+        let other = index(&pool, &pool.pick(Some(&b)));
+        assert_ne!(home, other);
+        assert!((0..4).all(|_| index(&pool, &pool.pick(Some(&a))) == home));
+
+        // An ended session releases its pin
+        drop(a);
+        assert_eq!(pool.live_pins().len(), 1);
+    }
+
+    #[test]
+    fn test_scale_up_bounds() {
+        let limits = serde_json::json!({ "pool": { "min": 1, "max": 2, "scaleUpQueueDepth": 2 } });
+        assert!(!pool(limits.clone(), &[1]).saturated());
+        assert!(pool(limits.clone(), &[2]).saturated());
+        assert!(!pool(limits.clone(), &[2, 0]).saturated());
+        assert!(!pool(limits, &[2, 2]).saturated(), "already at max");
+    }
+
+    #[test]
+    fn test_scale_down_bounds() {
+        let long_idle = std::time::Duration::from_secs(60);
+        let idle_after = std::time::Duration::from_secs(30);
+
+        let autoscaled = pool(serde_json::json!({ "pool": { "min": 2, "max": 4 } }), &[]);
+        for load in [0, 1, 0, 0] {
+            autoscaled.add(instance(load, long_idle));
+        }
+        let busy = autoscaled.instances()[1].clone();
+        assert_eq!(autoscaled.remove_idle(idle_after).len(), 2);
+        let left = autoscaled.instances();
+        assert_eq!(left.len(), 2, "kept min");
+        assert!(left.iter().any(|p| Arc::ptr_eq(p, &busy)));
+
+        // With no minimum, the instance a live session is pinned to still stays
+        let sticky = pool(serde_json::json!({ "pool": { "min": 0, "max": 3 }, "routing": "sticky" }), &[]);
+        for _ in 0..3 {
+            sticky.add(instance(0, long_idle));
+        }
+        let session = Arc::new(ClientPeer::sse(None));
+        let pinned = sticky.pick(Some(&session));
+        if let Ok(mut used) = pinned.last_used.lock() {
+            *used = Instant::now() - long_idle;
+        }
+        assert_eq!(sticky.remove_idle(idle_after).len(), 2);
+        assert!(Arc::ptr_eq(&sticky.instances()[0], &pinned));
+    }
+}
@@ -0,0 +1,100 @@
+//! Single-flight for read-only tool calls. When several sessions send the
+//! same call at once, the first one (the leader) goes to the server and the
+//! others wait for its result instead of sending N identical requests.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde_json::Value;
+use tokio::sync::watch;
+
+type Outcome = Option<Result<Value, String>>;
+
+#[derive(Default)]
+pub struct Flights {
+    /// In-flight calls by `result_cache::key`.
+    map: Mutex<HashMap<String, watch::Receiver<Outcome>>>,
+    /// Calls served by another call's request, per server.
+    coalesced: Mutex<HashMap<String, u64>>,
+}
+
+pub enum Flight<'a> {
+    /// Send the call, then `finish`.
+    Leader(Leader<'a>),
+    /// An identical call is in flight; `wait` for it.
+    Follower(watch::Receiver<Outcome>),
+}
+
+pub struct Leader<'a> {
+    flights: &'a Flights,
+    key: String,
+    tx: watch::Sender<Outcome>,
+}
+
+impl Flights {
+    pub fn join(&self, server: &str, key: &str) -> Flight<'_> {
+        let mut map = self.map.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(rx) = map.get(key) {
+            *self.coalesced.lock().unwrap_or_else(|e| e.into_inner()).entry(server.to_string()).or_default() += 1;
+            return Flight::Follower(rx.clone());
+        }
+        let (tx, rx) = watch::channel(None);
+        map.insert(key.to_string(), rx);
+        Flight::Leader(Leader { flights: self, key: key.to_string(), tx })
+    }
+
+    pub fn stats(&self) -> Vec<(String, u64)> {
+        let coalesced = self.coalesced.lock().unwrap_or_else(|e| e.into_inner());
+        coalesced.iter().map(|(name, n)| (name.clone(), *n)).collect()
+    }
+}
+
+impl Leader<'_> {
+    /// Hand a copy of the result to every follower.
+    pub fn finish(self, result: &Result<Value, String>) {
+        let _ = self.tx.send(Some(result.clone()));
+    }
+}
+
+impl Drop for Leader<'_> {
+    fn drop(&mut self) {
+        self.flights.map.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.key);
+    }
+}
+
+/// The leader's result, or None if it went away or its client cancelled it;
+/// the follower should then send the call itself.
+pub async fn wait(mut rx: watch::Receiver<Outcome>) -> Option<Result<Value, String>> {
+    let outcome = rx.wait_for(Option::is_some).await.ok()?.clone()?;
+    match outcome {
+        Err(e) if crate::child::is_cancelled(&e) => None,
+        outcome => Some(outcome),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_followers_share_the_leader_result() {
+        let flights = Flights::default();
+        let Flight::Leader(leader) = flights.join("db", "k") else { panic!("first call leads") };
+        let Flight::Follower(rx) = flights.join("db", "k") else { panic!("second call follows") };
+        leader.finish(&Ok(json!({ "rows": 1 })));
+        assert_eq!(wait(rx).await, Some(Ok(json!({ "rows": 1 }))));
+        assert!(matches!(flights.join("db", "k"), Flight::Leader(_)));
+
+        // A cancelled or vanished leader sends followers back to the server
+        let Flight::Leader(leader) = flights.join("db", "k") else { panic!() };
+        let Flight::Follower(rx) = flights.join("db", "k") else { panic!() };
+        leader.finish(&Err("Cancelled: user".into()));
+        assert_eq!(wait(rx).await, None);
+        let Flight::Leader(leader) = flights.join("db", "k") else { panic!() };
+        let Flight::Follower(rx) = flights.join("db", "k") else { panic!() };
+        drop(leader);
+        assert_eq!(wait(rx).await, None);
+        assert_eq!(flights.stats(), vec![("db".to_string(), 3)]);
+    }
+}
@@ -8,7 +8,492 @@ pub struct ServerConfig {
     pub command: String,
     pub args: Vec<String>,
     pub env: HashMap<String, String>,
-    pub pool: usize,
+    pub launch: Launch,
+    pub pool: PoolConfig,
+    pub routing: Routing,
+    pub scope: Scope,
+    pub transport: Transport,
+    pub timeouts: Timeouts,
+    pub limits: Limits,
+    pub sandbox: Option<Sandbox>,
+    pub circuit_breaker: CircuitBreaker,
+    pub throttle: Throttle,
+    pub cache: Caching,
+}
+
+/// Where and with which environment a stdio server starts: `cwd`, `envFile`
+/// (a dotenv file) and `inheritEnv`. The environment is built at every spawn,
+/// so edits to the env file apply on the next restart.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Launch {
+    pub cwd: Option<String>,
+    pub env_file: Option<String>,
+    pub inherit_env: InheritEnv,
+}
+
+/// Which of McpHub's own environment variables a server sees (`"inheritEnv"`).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum InheritEnv {
+    #[default]
+    All,
+    /// Only `ESSENTIAL_ENV`.
+    None,
+    /// `ESSENTIAL_ENV` plus these names.
+    Only(Vec<String>),
+}
+
+/// Passed even with `"inheritEnv": "none"`: without them most servers can't
+/// find their interpreter, home directory or temp dir. None of them are secrets.
+const ESSENTIAL_ENV: &[&str] = &[
+    "PATH", "HOME", "USER", "LOGNAME", "SHELL", "LANG", "LC_ALL", "TZ", "TMPDIR",
+    "SYSTEMROOT", "TEMP", "TMP", "USERPROFILE", "APPDATA", "LOCALAPPDATA",
+];
+
+impl InheritEnv {
+    fn allows(&self, key: &str) -> bool {
+        match self {
+            InheritEnv::All => true,
+            InheritEnv::None => ESSENTIAL_ENV.contains(&key),
+            InheritEnv::Only(names) => ESSENTIAL_ENV.contains(&key) || names.iter().any(|n| n == key),
+        }
+    }
+}
+
+fn parse_inherit_env(name: &str, value: Option<&Value>) -> InheritEnv {
+    match value {
+        None => InheritEnv::All,
+        Some(Value::Array(names)) => InheritEnv::Only(names.iter().filter_map(|v| v.as_str().map(String::from)).collect()),
+        Some(v) => match v.as_str() {
+            Some("all") => InheritEnv::All,
+            Some("none") => InheritEnv::None,
+            _ => {
+                eprintln!("[McpHub][WARN] {}: unknown inheritEnv {}, using all", name, v);
+                InheritEnv::All
+            }
+        },
+    }
+}
+
+/// `KEY=value` lines of a dotenv file. Blank lines and `#` comments are
+/// skipped, an `export ` prefix is allowed and matching quotes are removed.
+pub fn parse_env_file(content: &str) -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = match line.split_once('=') {
+            Some(kv) => kv,
+            None => continue,
+        };
+        let value = value.trim();
+        let unquoted = ['"', '\''].iter().find_map(|q| value.strip_prefix(*q).and_then(|v| v.strip_suffix(*q)));
+        vars.insert(key.trim().to_string(), unquoted.unwrap_or(value).to_string());
+    }
+    vars
+}
+
+/// Instance limits for a server: `"pool": 3` runs exactly 3, while
+/// `"pool": {"min": 1, "max": 4, "scaleUpQueueDepth": 2, "scaleDownIdleSecs": 120}`
+/// starts one and adds more while calls queue up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolConfig {
+    pub min: usize,
+    pub max: usize,
+    /// Outstanding calls every instance must have before another is started.
+    pub scale_up_queue_depth: usize,
+    /// Extra instances idle this long are stopped by the reaper.
+    pub scale_down_idle_secs: u64,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self { min: 1, max: 1, scale_up_queue_depth: 1, scale_down_idle_secs: 60 }
+    }
+}
+
+impl PoolConfig {
+    fn fixed(size: usize) -> Self {
+        let size = size.max(1);
+        Self { min: size, max: size, ..Default::default() }
+    }
+}
+
+fn parse_pool(value: Option<&Value>) -> PoolConfig {
+    let value = match value {
+        Some(v) => v,
+        None => return PoolConfig::default(),
+    };
+    if let Some(size) = value.as_u64() {
+        return PoolConfig::fixed(size as usize);
+    }
+    let defaults = PoolConfig::default();
+    let field = |key: &str| value.get(key).and_then(|v| v.as_u64());
+    let min = field("min").map(|v| v as usize).unwrap_or(defaults.min).max(1);
+    PoolConfig {
+        min,
+        max: field("max").map(|v| v as usize).unwrap_or(min).max(min),
+        scale_up_queue_depth: field("scaleUpQueueDepth").map(|v| v as usize).unwrap_or(defaults.scale_up_queue_depth).max(1),
+        scale_down_idle_secs: field("scaleDownIdleSecs").unwrap_or(defaults.scale_down_idle_secs),
+    }
+}
+
+/// How calls are spread across a pool's instances (`"routing"`).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Routing {
+    #[default]
+    RoundRobin,
+    /// An idle instance if there is one, else the one with the fewest calls outstanding.
+    LeastBusy,
+    /// Each client session stays on the instance it first used, for stateful servers.
+    Sticky,
+}
+
+fn parse_routing(name: &str, value: Option<&Value>) -> Routing {
+    match value.and_then(|v| v.as_str()) {
+        None | Some("round_robin") => Routing::RoundRobin,
+        Some("least_busy") => Routing::LeastBusy,
+        Some("sticky") => Routing::Sticky,
+        Some(other) => {
+            eprintln!("[McpHub][WARN] {}: unknown routing '{}', using round_robin", name, other);
+            Routing::RoundRobin
+        }
+    }
+}
+
+/// Who shares a server's instances (`"scope"`).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Scope {
+    /// One pool serves every client.
+    #[default]
+    Shared,
+    /// Each client session gets a private instance, started on its first call
+    /// and stopped when the session ends.
+    Session,
+}
+
+fn parse_scope(name: &str, value: Option<&Value>) -> Scope {
+    match value.and_then(|v| v.as_str()) {
+        None | Some("shared") => Scope::Shared,
+        Some("session") => Scope::Session,
+        Some(other) => {
+            eprintln!("[McpHub][WARN] {}: unknown scope '{}', using shared", name, other);
+            Scope::Shared
+        }
+    }
+}
+
+/// Resource limits for a stdio server (`"limits"`), applied on Linux. All are
+/// optional: `memoryMb` (resident memory), `cpuSecs`, `maxProcs` and
+/// `wallClockSecs` count the server's whole process group; all but the wall
+/// clock also cap each process with an rlimit, as `nofile` does.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Limits {
+    pub memory_mb: Option<u64>,
+    pub cpu_secs: Option<u64>,
+    pub nofile: Option<u64>,
+    pub max_procs: Option<u64>,
+    pub wall_clock_secs: Option<u64>,
+}
+
+impl Limits {
+    /// Any limit that has to be watched while the server runs.
+    pub fn watched(&self) -> bool {
+        self.memory_mb.is_some() || self.cpu_secs.is_some() || self.max_procs.is_some() || self.wall_clock_secs.is_some()
+    }
+}
+
+fn parse_limits(value: Option<&Value>) -> Limits {
+    let field = |key: &str| value.and_then(|v| v.get(key)).and_then(|v| v.as_u64()).filter(|n| *n > 0);
+    Limits {
+        memory_mb: field("memoryMb"),
+        cpu_secs: field("cpuSecs"),
+        nofile: field("nofile"),
+        max_procs: field("maxProcs"),
+        wall_clock_secs: field("wallClockSecs"),
+    }
+}
+
+/// Confinement for a stdio server (`"sandbox"`), applied on Linux before exec.
+/// Listing `readOnly` or `readWrite` paths restricts the filesystem to those
+/// (plus the system directories) with Landlock; `"network": false` blocks
+/// internet sockets with seccomp.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sandbox {
+    pub read_only: Vec<String>,
+    pub read_write: Vec<String>,
+    /// Either path list was given, even empty.
+    pub restrict_paths: bool,
+    pub network: bool,
+}
+
+fn parse_sandbox(value: Option<&Value>) -> Option<Sandbox> {
+    let value = value?.as_object()?;
+    let paths = |key: &str| -> Vec<String> {
+        value.get(key).and_then(|v| v.as_array())
+            .map(|arr| arr.iter().filter_map(|v| v.as_str().map(expand_home)).collect())
+            .unwrap_or_default()
+    };
+    Some(Sandbox {
+        read_only: paths("readOnly"),
+        read_write: paths("readWrite"),
+        restrict_paths: value.contains_key("readOnly") || value.contains_key("readWrite"),
+        network: value.get("network").and_then(|v| v.as_bool()).unwrap_or(true),
+    })
+}
+
+fn expand_home(path: &str) -> String {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().unwrap_or_default().join(rest).to_string_lossy().into_owned(),
+        None => path.to_string(),
+    }
+}
+
+/// When calls to a server stop getting through (`"circuitBreaker"`, or `false`
+/// to turn it off): after `failures` consecutive failures, or an `errorRate`
+/// over the last minute once it saw `minCalls` calls, calls fail fast for
+/// `cooldownSecs` before one trial call is let through.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitBreaker {
+    pub enabled: bool,
+    pub failures: u32,
+    pub error_rate: f64,
+    pub min_calls: u32,
+    pub cooldown_secs: u64,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self { enabled: true, failures: 5, error_rate: 0.5, min_calls: 10, cooldown_secs: 30 }
+    }
+}
+
+fn parse_circuit_breaker(value: Option<&Value>) -> CircuitBreaker {
+    let defaults = CircuitBreaker::default();
+    let value = match value {
+        Some(Value::Bool(enabled)) => return CircuitBreaker { enabled: *enabled, ..defaults },
+        Some(v) => v,
+        None => return defaults,
+    };
+    let field = |key: &str| value.get(key).and_then(|v| v.as_u64()).filter(|n| *n > 0);
+    CircuitBreaker {
+        enabled: value.get("enabled").and_then(|v| v.as_bool()).unwrap_or(true),
+        failures: field("failures").map(|n| n as u32).unwrap_or(defaults.failures),
+        error_rate: value.get("errorRate").and_then(|v| v.as_f64()).filter(|r| *r > 0.0 && *r <= 1.0).unwrap_or(defaults.error_rate),
+        min_calls: field("minCalls").map(|n| n as u32).unwrap_or(defaults.min_calls),
+        cooldown_secs: field("cooldownSecs").unwrap_or(defaults.cooldown_secs),
+    }
+}
+
+/// Call admission for a server: `maxConcurrent` and `rateLimit` for the whole
+/// server, `toolMaxConcurrent` and `toolRateLimits` per tool. Calls over a
+/// limit queue for up to `queueTimeout` seconds.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Throttle {
+    pub max_concurrent: Option<usize>,
+    pub rate_limit: Option<RateLimit>,
+    pub tool_max_concurrent: HashMap<String, usize>,
+    pub tool_rate_limits: HashMap<String, RateLimit>,
+    pub queue_timeout: Option<u64>,
+}
+
+/// Result cache (`"cache": true`, or an object to tune it). Calls to tools
+/// marked `readOnlyHint` are answered from the cache for `ttl` seconds;
+/// `tools` sets the TTL per tool, 0 turning caching off for that tool and a
+/// positive value opting in a tool that isn't marked read-only.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Caching {
+    pub enabled: bool,
+    pub ttl_secs: u64,
+    pub tools: HashMap<String, u64>,
+}
+
+/// TTL for read-only tools when `cache` doesn't set `ttl`.
+const DEFAULT_CACHE_TTL_SECS: u64 = 60;
+
+impl Caching {
+    /// How long a result of `tool` stays cached, or None if it isn't cached.
+    pub fn ttl(&self, tool: &str, read_only: bool) -> Option<std::time::Duration> {
+        if !self.enabled {
+            return None;
+        }
+        let secs = self.tools.get(tool).copied().or(read_only.then_some(self.ttl_secs))?;
+        (secs > 0).then(|| std::time::Duration::from_secs(secs))
+    }
+}
+
+fn parse_caching(value: Option<&Value>) -> Caching {
+    let enabled = Caching { enabled: true, ttl_secs: DEFAULT_CACHE_TTL_SECS, tools: HashMap::new() };
+    match value {
+        Some(Value::Bool(true)) => enabled,
+        Some(Value::Object(obj)) => Caching {
+            ttl_secs: obj.get("ttl").and_then(|v| v.as_u64()).unwrap_or(enabled.ttl_secs),
+            tools: obj.get("tools").and_then(|v| v.as_object())
+                .map(|tools| tools.iter().filter_map(|(k, v)| v.as_u64().map(|t| (k.clone(), t))).collect())
+                .unwrap_or_default(),
+            ..enabled
+        },
+        _ => Caching::default(),
+    }
+}
+
+/// How long a throttled call may wait for its turn by default.
+const DEFAULT_QUEUE_TIMEOUT_SECS: u64 = 30;
+
+impl Throttle {
+    pub fn queue_timeout_secs(&self) -> u64 {
+        self.queue_timeout.unwrap_or(DEFAULT_QUEUE_TIMEOUT_SECS)
+    }
+}
+
+/// A token bucket: `{"perSecond": 2}`, `{"perMinute": 30, "burst": 5}` or
+/// `{"perHour": 500}`. `burst` calls may go back to back (default 1).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub count: u64,
+    pub period_secs: u64,
+    pub burst: u64,
+}
+
+impl RateLimit {
+    pub fn per_sec(&self) -> f64 {
+        self.count as f64 / self.period_secs as f64
+    }
+}
+
+impl std::fmt::Display for RateLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let unit = match self.period_secs {
+            1 => "s",
+            60 => "min",
+            _ => "h",
+        };
+        write!(f, "{}/{}", self.count, unit)
+    }
+}
+
+fn parse_rate_limit(value: &Value) -> Option<RateLimit> {
+    let field = |key: &str| value.get(key).and_then(|v| v.as_u64()).filter(|n| *n > 0);
+    let (count, period_secs) = [("perSecond", 1), ("perMinute", 60), ("perHour", 3600)]
+        .iter()
+        .find_map(|(key, secs)| field(key).map(|n| (n, *secs)))?;
+    Some(RateLimit { count, period_secs, burst: field("burst").unwrap_or(1) })
+}
+
+fn parse_throttle(config: &Value) -> Throttle {
+    let count = |v: &Value| v.as_u64().filter(|n| *n > 0).map(|n| n as usize);
+    Throttle {
+        max_concurrent: config.get("maxConcurrent").and_then(count),
+        rate_limit: config.get("rateLimit").and_then(parse_rate_limit),
+        tool_max_concurrent: config.get("toolMaxConcurrent").and_then(|v| v.as_object())
+            .map(|obj| obj.iter().filter_map(|(k, v)| count(v).map(|n| (k.clone(), n))).collect())
+            .unwrap_or_default(),
+        tool_rate_limits: config.get("toolRateLimits").and_then(|v| v.as_object())
+            .map(|obj| obj.iter().filter_map(|(k, v)| parse_rate_limit(v).map(|r| (k.clone(), r))).collect())
+            .unwrap_or_default(),
+        queue_timeout: config.get("queueTimeout").and_then(|v| v.as_u64()).filter(|t| *t > 0),
+    }
+}
+
+/// Hub-wide default for a request, when the server sets no `timeout`.
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+/// Default for `initialize` and the first `tools/list` (npx installs can be slow).
+const DEFAULT_STARTUP_TIMEOUT_SECS: u64 = 30;
+/// Grace period between SIGTERM and SIGKILL when a child is stopped.
+const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 5;
+/// Health pings never wait longer than this, whatever the server's `timeout`.
+const PING_TIMEOUT_SECS: u64 = 5;
+
+/// Deadlines for one server, in seconds (`timeout`, `startupTimeout`,
+/// `shutdownTimeout`, `toolTimeouts`).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Timeouts {
+    pub request: Option<u64>,
+    pub startup: Option<u64>,
+    pub shutdown: Option<u64>,
+    pub tools: HashMap<String, u64>,
+}
+
+impl Timeouts {
+    /// Deadline for `method`; `tools/call` honors the per-tool override first.
+    pub fn request_secs(&self, method: &str, params: &Value) -> u64 {
+        if method == "tools/call" {
+            let tool = params.get("name").and_then(|v| v.as_str()).unwrap_or("");
+            if let Some(secs) = self.tools.get(tool) {
+                return *secs;
+            }
+        }
+        self.request.unwrap_or(DEFAULT_REQUEST_TIMEOUT_SECS)
+    }
+
+    pub fn startup_secs(&self) -> u64 {
+        self.startup.unwrap_or(DEFAULT_STARTUP_TIMEOUT_SECS)
+    }
+
+    pub fn shutdown_secs(&self) -> u64 {
+        self.shutdown.unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT_SECS)
+    }
+
+    /// A server with a short `timeout` is also pinged with it.
+    pub fn ping_secs(&self) -> u64 {
+        self.request.unwrap_or(PING_TIMEOUT_SECS).min(PING_TIMEOUT_SECS)
+    }
+}
+
+/// How McpHub talks to an upstream server.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Transport {
+    /// Spawned child process speaking JSON-RPC over stdin/stdout.
+    #[default]
+    Stdio,
+    /// Remote server using the Streamable HTTP transport (single endpoint, POST + SSE).
+    StreamableHttp {
+        url: String,
+        headers: HashMap<String, String>,
+    },
+    /// Remote server using the legacy HTTP+SSE transport (`GET /sse` + POST endpoint).
+    Sse {
+        url: String,
+        headers: HashMap<String, String>,
+    },
+}
+
+impl ServerConfig {
+    /// Human-readable target: the command line for stdio, the URL for remote servers.
+    pub fn endpoint(&self) -> String {
+        match &self.transport {
+            Transport::Stdio => format!("{} {}", self.command, self.args.join(" ")).trim_end().to_string(),
+            Transport::StreamableHttp { url, .. } | Transport::Sse { url, .. } => url.clone(),
+        }
+    }
+
+    pub fn is_remote(&self) -> bool {
+        self.transport != Transport::Stdio
+    }
+
+    /// Full environment for a stdio child: the inherited variables `inheritEnv`
+    /// allows, then `envFile`, then `env` (later ones win) with its secret
+    /// references resolved.
+    pub fn environment(&self) -> Result<HashMap<String, String>, String> {
+        let mut vars: HashMap<String, String> = std::env::vars()
+            .filter(|(k, _)| self.launch.inherit_env.allows(k))
+            .collect();
+        if let Some(file) = &self.launch.env_file {
+            // Relative to the server's cwd, or to ~/.McpHub where config.json lives
+            let base = match &self.launch.cwd {
+                Some(cwd) => PathBuf::from(cwd),
+                None => dirs::home_dir().unwrap_or_default().join(".McpHub"),
+            };
+            let path = base.join(file);
+            let content = fs::read_to_string(&path)
+                .map_err(|e| format!("Cannot read envFile {}: {}", path.display(), e))?;
+            vars.extend(parse_env_file(&content));
+        }
+        vars.extend(crate::secrets::resolve_map(&self.env)?);
+        Ok(vars)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -21,6 +506,7 @@ pub enum Mode {
 pub enum Preload {
     All,
     None,
+    #[allow(dead_code)]
     Some(Vec<String>),
 }
 
@@ -30,10 +516,12 @@ pub struct ProxyConfig {
     pub mode: Mode,
     pub preload: Preload,
     pub idle_timeout_ms: u64,
+    #[allow(dead_code)]
     pub preload_delay_ms: u64,
     pub health_check_interval_secs: u64,
     pub health_auto_restart: bool,
     pub health_notifications: bool,
+    pub large_results: LargeResults,
 }
 
 impl Default for ProxyConfig {
@@ -47,10 +535,44 @@ impl Default for ProxyConfig {
             health_check_interval_secs: 30,
             health_auto_restart: true,
             health_notifications: true,
+            large_results: LargeResults::default(),
         }
     }
 }
 
+/// Oversized tool results (`settings.largeResults`). Off unless it is `true`
+/// or sets `maxChars`: a result with more than `maxChars` characters of text
+/// is kept hub-side for `ttl` seconds, and the client gets the first
+/// `previewChars` and a handle to page through the rest with `fetch_result`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LargeResults {
+    pub enabled: bool,
+    pub max_chars: usize,
+    pub preview_chars: usize,
+    pub ttl_secs: u64,
+}
+
+impl Default for LargeResults {
+    fn default() -> Self {
+        Self { enabled: false, max_chars: 100_000, preview_chars: 4_000, ttl_secs: 900 }
+    }
+}
+
+fn parse_large_results(value: &Value) -> LargeResults {
+    let defaults = LargeResults::default();
+    if let Value::Bool(enabled) = value {
+        return LargeResults { enabled: *enabled, ..defaults };
+    }
+    let field = |key: &str| value.get(key).and_then(|v| v.as_u64()).filter(|n| *n > 0);
+    let max_chars = field("maxChars").map(|n| n as usize).unwrap_or(defaults.max_chars);
+    LargeResults {
+        enabled: value.get("enabled").and_then(|v| v.as_bool()).unwrap_or(field("maxChars").is_some()),
+        max_chars,
+        preview_chars: field("previewChars").map(|n| n as usize).unwrap_or(defaults.preview_chars).min(max_chars),
+        ttl_secs: field("ttl").unwrap_or(defaults.ttl_secs),
+    }
+}
+
 fn is_self(name: &str, config: &Value) -> bool {
     let lower = name.to_lowercase();
     if lower == "mcphub" || lower == "mcp-hub" || lower == "mcp-on-demand" { return true; }
@@ -58,6 +580,10 @@ fn is_self(name: &str, config: &Value) -> bool {
         let cmd_lower = cmd.to_lowercase();
         if cmd_lower.contains("mcphub") || cmd_lower.contains("mcp-on-demand") { return true; }
     }
+    if let Some(url) = config.get("url").and_then(|v| v.as_str()) {
+        // Our own SSE / HTTP endpoint, as written into editor configs by `McpHub install`
+        if url.contains("127.0.0.1:24680") || url.contains("localhost:24680") { return true; }
+    }
     if let Some(args) = config.get("args").and_then(|v| v.as_array()) {
         if args.iter().any(|a| a.as_str().map(|s| {
             let s_lower = s.to_lowercase();
@@ -84,20 +610,75 @@ fn parse_servers(json: &Value) -> HashMap<String, ServerConfig> {
             eprintln!("[McpHub][INFO] Skipped disabled: {}", name);
             continue;
         }
-        if let Some(cmd) = config.get("command").and_then(|v| v.as_str()) {
-            let args: Vec<String> = config.get("args").and_then(|v| v.as_array())
-                .map(|arr| arr.iter().filter_map(|v| v.as_str().map(String::from)).collect())
-                .unwrap_or_default();
-            let env: HashMap<String, String> = config.get("env").and_then(|v| v.as_object())
-                .map(|obj| obj.iter().filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string()))).collect())
-                .unwrap_or_default();
-            let pool = config.get("pool").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
-            result.insert(name.clone(), ServerConfig { command: cmd.to_string(), args, env, pool });
+        if let Some(server) = parse_server(name, config) {
+            result.insert(name.clone(), server);
         }
     }
     result
 }
 
+/// One `mcpServers` entry, or None if it has neither `command` nor a usable `url`.
+pub fn parse_server(name: &str, config: &Value) -> Option<ServerConfig> {
+    let args: Vec<String> = config.get("args").and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+    let env = string_map(config.get("env"));
+    let pool = parse_pool(config.get("pool"));
+    let routing = parse_routing(name, config.get("routing"));
+    let scope = parse_scope(name, config.get("scope"));
+    let limits = parse_limits(config.get("limits"));
+    let sandbox = parse_sandbox(config.get("sandbox"));
+    let circuit_breaker = parse_circuit_breaker(config.get("circuitBreaker"));
+    let throttle = parse_throttle(config);
+    let cache = parse_caching(config.get("cache"));
+    let launch = Launch {
+        cwd: config.get("cwd").and_then(|v| v.as_str()).map(expand_home),
+        env_file: config.get("envFile").and_then(|v| v.as_str()).map(expand_home),
+        inherit_env: parse_inherit_env(name, config.get("inheritEnv")),
+    };
+    let timeouts = Timeouts {
+        request: config.get("timeout").and_then(|v| v.as_u64()).filter(|t| *t > 0),
+        startup: config.get("startupTimeout").and_then(|v| v.as_u64()).filter(|t| *t > 0),
+        shutdown: config.get("shutdownTimeout").and_then(|v| v.as_u64()),
+        tools: config.get("toolTimeouts").and_then(|v| v.as_object())
+            .map(|obj| obj.iter().filter_map(|(k, v)| v.as_u64().filter(|t| *t > 0).map(|t| (k.clone(), t))).collect())
+            .unwrap_or_default(),
+    };
+
+    if let Some(cmd) = config.get("command").and_then(|v| v.as_str()) {
+        Some(ServerConfig {
+            command: cmd.to_string(), args, env, launch, pool, routing, scope, transport: Transport::Stdio,
+            timeouts, limits, sandbox, circuit_breaker, throttle, cache,
+        })
+    } else if let Some(url) = config.get("url").and_then(|v| v.as_str()) {
+        let kind = config.get("type").or_else(|| config.get("transport")).and_then(|v| v.as_str()).unwrap_or("");
+        let headers = string_map(config.get("headers"));
+        // Without an explicit type, a URL ending in /sse is the legacy transport (as in Cursor)
+        let is_sse_path = url.split('?').next().unwrap_or(url).trim_end_matches('/').ends_with("/sse");
+        let transport = match kind {
+            "sse" => Transport::Sse { url: url.to_string(), headers },
+            "" if is_sse_path => Transport::Sse { url: url.to_string(), headers },
+            "" | "http" | "streamable-http" | "streamableHttp" => Transport::StreamableHttp { url: url.to_string(), headers },
+            other => {
+                eprintln!("[McpHub][WARN] Skipped {}: unsupported transport type '{}'", name, other);
+                return None;
+            }
+        };
+        Some(ServerConfig {
+            command: String::new(), args, env, launch, pool, routing, scope, transport,
+            timeouts, limits, sandbox, circuit_breaker, throttle, cache,
+        })
+    } else {
+        None
+    }
+}
+
+fn string_map(value: Option<&Value>) -> HashMap<String, String> {
+    value.and_then(|v| v.as_object())
+        .map(|obj| obj.iter().filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string()))).collect())
+        .unwrap_or_default()
+}
+
 fn load_dedicated_config() -> Option<ProxyConfig> {
     let home = dirs::home_dir()?;
     let path = home.join(".McpHub").join("config.json");
@@ -128,6 +709,9 @@ fn load_dedicated_config() -> Option<ProxyConfig> {
                 config.health_notifications = notify;
             }
         }
+        if let Some(large_results) = settings.get("largeResults") {
+            config.large_results = parse_large_results(large_results);
+        }
     }
     Some(config)
 }
@@ -236,6 +820,232 @@ mod tests {
         assert_eq!(github.env.get("GITHUB_TOKEN").unwrap(), "123");
     }
 
+    #[test]
+    fn test_parse_servers_remote() {
+        let json = json!({
+            "mcpServers": {
+                "linear": {
+                    "url": "https://mcp.linear.app/mcp",
+                    "headers": { "Authorization": "Bearer abc" }
+                },
+                "legacy": { "url": "https://internal.example.com/sse" },
+                "legacy_typed": { "url": "https://internal.example.com/events", "type": "sse" },
+                "self_url": { "url": "http://127.0.0.1:24680/sse" }
+            }
+        });
+
+        let servers = parse_servers(&json);
+        assert_eq!(servers.len(), 3);
+        assert!(matches!(servers["legacy"].transport, Transport::Sse { .. }));
+        assert!(matches!(servers["legacy_typed"].transport, Transport::Sse { .. }));
+        let linear = &servers["linear"];
+        assert!(linear.is_remote());
+        assert_eq!(linear.endpoint(), "https://mcp.linear.app/mcp");
+        match &linear.transport {
+            Transport::StreamableHttp { headers, .. } => assert_eq!(headers["Authorization"], "Bearer abc"),
+            other => panic!("unexpected transport: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_timeouts() {
+        let json = json!({
+            "mcpServers": {
+                "ci": {
+                    "command": "ci-mcp",
+                    "timeout": 120,
+                    "startupTimeout": 60,
+                    "toolTimeouts": { "deploy": 600, "bogus": "fast" }
+                },
+                "fs": { "command": "fs-mcp", "timeout": 2, "shutdownTimeout": 0 },
+                "plain": { "command": "plain-mcp" }
+            }
+        });
+
+        let servers = parse_servers(&json);
+        let ci = &servers["ci"].timeouts;
+        assert_eq!(ci.request_secs("tools/call", &json!({"name": "deploy"})), 600);
+        assert_eq!(ci.request_secs("tools/call", &json!({"name": "lint"})), 120);
+        assert_eq!(ci.request_secs("resources/read", &json!({})), 120);
+        assert_eq!(ci.startup_secs(), 60);
+        assert_eq!(ci.tools.len(), 1);
+        assert_eq!(ci.ping_secs(), 5);
+        assert_eq!(servers["fs"].timeouts.ping_secs(), 2);
+        assert_eq!(servers["fs"].timeouts.shutdown_secs(), 0);
+        let plain = &servers["plain"].timeouts;
+        assert_eq!(plain.request_secs("tools/call", &json!({"name": "x"})), DEFAULT_REQUEST_TIMEOUT_SECS);
+        assert_eq!(plain.startup_secs(), DEFAULT_STARTUP_TIMEOUT_SECS);
+        assert_eq!(plain.shutdown_secs(), DEFAULT_SHUTDOWN_TIMEOUT_SECS);
+    }
+
+    #[test]
+    fn test_parse_pool() {
+        let json = json!({
+            "mcpServers": {
+                "fixed": { "command": "a", "pool": 3 },
+                "auto": { "command": "b", "pool": { "min": 2, "max": 5, "scaleUpQueueDepth": 4, "scaleDownIdleSecs": 30 }, "routing": "sticky" },
+                "bad_max": { "command": "c", "pool": { "min": 3, "max": 1 } },
+                "single": { "command": "d", "scope": "session" }
+            }
+        });
+
+        let servers = parse_servers(&json);
+        assert_eq!(servers["fixed"].pool, PoolConfig { min: 3, max: 3, ..Default::default() });
+        assert_eq!(servers["auto"].pool, PoolConfig { min: 2, max: 5, scale_up_queue_depth: 4, scale_down_idle_secs: 30 });
+        assert_eq!((servers["bad_max"].pool.min, servers["bad_max"].pool.max), (3, 3));
+        assert_eq!(servers["single"].pool, PoolConfig::default());
+        assert_eq!(servers["auto"].routing, Routing::Sticky);
+        assert_eq!(servers["single"].routing, Routing::RoundRobin);
+        assert_eq!(servers["single"].scope, Scope::Session);
+        assert_eq!(servers["fixed"].scope, Scope::Shared);
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        let json = json!({
+            "mcpServers": {
+                "browser": { "command": "a", "limits": { "memoryMb": 2048, "nofile": 1024, "cpuSecs": 0, "maxProcs": "many" } },
+                "plain": { "command": "b" }
+            }
+        });
+
+        let servers = parse_servers(&json);
+        let browser = &servers["browser"].limits;
+        assert_eq!(browser, &Limits { memory_mb: Some(2048), nofile: Some(1024), ..Default::default() });
+        assert!(browser.watched());
+        assert!(!servers["plain"].limits.watched());
+    }
+
+    #[test]
+    fn test_parse_sandbox() {
+        let json = json!({
+            "mcpServers": {
+                "fs": { "command": "a", "sandbox": { "readOnly": ["/srv/docs"], "readWrite": ["~/scratch"] } },
+                "offline": { "command": "b", "sandbox": { "network": false } },
+                "plain": { "command": "c" }
+            }
+        });
+
+        let servers = parse_servers(&json);
+        let fs = servers["fs"].sandbox.as_ref().unwrap();
+        assert_eq!(fs.read_only, vec!["/srv/docs"]);
+        assert!(fs.read_write[0].ends_with("scratch") && !fs.read_write[0].starts_with('~'));
+        assert!(fs.restrict_paths && fs.network);
+        let offline = servers["offline"].sandbox.as_ref().unwrap();
+        assert!(!offline.restrict_paths && !offline.network);
+        assert!(servers["plain"].sandbox.is_none());
+    }
+
+    #[test]
+    fn test_parse_throttle() {
+        let json = json!({
+            "mcpServers": {
+                "github": {
+                    "command": "gh-mcp",
+                    "maxConcurrent": 4,
+                    "rateLimit": { "perMinute": 30, "burst": 5 },
+                    "toolMaxConcurrent": { "create_issue": 1, "bogus": 0 },
+                    "toolRateLimits": { "search_code": { "perSecond": 1 }, "bogus": { "perDay": 1 } },
+                    "queueTimeout": 10
+                },
+                "plain": { "command": "plain-mcp" }
+            }
+        });
+
+        let servers = parse_servers(&json);
+        let gh = &servers["github"].throttle;
+        assert_eq!(gh.max_concurrent, Some(4));
+        let rate = gh.rate_limit.unwrap();
+        assert_eq!((rate.burst, rate.per_sec(), rate.to_string()), (5, 0.5, "30/min".to_string()));
+        assert_eq!(gh.tool_max_concurrent, HashMap::from([("create_issue".to_string(), 1)]));
+        assert_eq!(gh.tool_rate_limits.len(), 1);
+        assert_eq!(gh.tool_rate_limits["search_code"].burst, 1);
+        assert_eq!(gh.queue_timeout_secs(), 10);
+        assert_eq!(servers["plain"].throttle, Throttle::default());
+        assert_eq!(servers["plain"].throttle.queue_timeout_secs(), DEFAULT_QUEUE_TIMEOUT_SECS);
+    }
+
+    #[test]
+    fn test_parse_caching() {
+        let json = json!({
+            "mcpServers": {
+                "docs": { "command": "a", "cache": { "ttl": 30, "tools": { "search": 5, "live_status": 0, "render": 120 } } },
+                "on": { "command": "b", "cache": true },
+                "plain": { "command": "c" }
+            }
+        });
+
+        let servers = parse_servers(&json);
+        let docs = &servers["docs"].cache;
+        let secs = |tool: &str, read_only: bool| docs.ttl(tool, read_only).map(|d| d.as_secs());
+        assert_eq!(secs("read_page", true), Some(30));
+        assert_eq!(secs("search", true), Some(5));
+        assert_eq!(secs("live_status", true), None);
+        assert_eq!(secs("render", false), Some(120));
+        assert_eq!(secs("write_page", false), None);
+        assert_eq!(servers["on"].cache.ttl("read", true).map(|d| d.as_secs()), Some(DEFAULT_CACHE_TTL_SECS));
+        assert_eq!(servers["plain"].cache.ttl("read", true), None);
+    }
+
+    #[test]
+    fn test_parse_large_results() {
+        let tuned = parse_large_results(&json!({ "maxChars": 20000, "previewChars": 50000, "ttl": 60 }));
+        assert_eq!(tuned, LargeResults { enabled: true, max_chars: 20000, preview_chars: 20000, ttl_secs: 60 });
+        assert!(!parse_large_results(&json!(false)).enabled);
+        assert!(parse_large_results(&json!(true)).enabled);
+        assert!(parse_large_results(&json!({ "ttl": 60, "enabled": true })).enabled);
+        assert!(!parse_large_results(&json!({ "previewChars": 100 })).enabled);
+        assert_eq!(parse_large_results(&json!({})), LargeResults::default());
+        assert!(!LargeResults::default().enabled);
+    }
+
+    #[test]
+    fn test_parse_circuit_breaker() {
+        let json = json!({
+            "mcpServers": {
+                "tuned": { "command": "a", "circuitBreaker": { "failures": 2, "errorRate": 0.8, "cooldownSecs": 10 } },
+                "off": { "command": "b", "circuitBreaker": false },
+                "plain": { "command": "c" }
+            }
+        });
+
+        let servers = parse_servers(&json);
+        let tuned = &servers["tuned"].circuit_breaker;
+        assert!(tuned.enabled);
+        assert_eq!((tuned.failures, tuned.error_rate, tuned.min_calls, tuned.cooldown_secs), (2, 0.8, 10, 10));
+        assert!(!servers["off"].circuit_breaker.enabled);
+        assert_eq!(servers["plain"].circuit_breaker, CircuitBreaker::default());
+    }
+
+    #[test]
+    fn test_parse_launch() {
+        let json = json!({
+            "mcpServers": {
+                "proj": { "command": "a", "cwd": "/srv/app", "envFile": ".env", "inheritEnv": ["GITHUB_TOKEN"] },
+                "bare": { "command": "b", "inheritEnv": "none" },
+                "plain": { "command": "c" }
+            }
+        });
+
+        let servers = parse_servers(&json);
+        let proj = &servers["proj"].launch;
+        assert_eq!(proj.cwd.as_deref(), Some("/srv/app"));
+        assert_eq!(proj.env_file.as_deref(), Some(".env"));
+        assert_eq!(proj.inherit_env, InheritEnv::Only(vec!["GITHUB_TOKEN".into()]));
+        assert!(proj.inherit_env.allows("PATH") && !proj.inherit_env.allows("AWS_SECRET_ACCESS_KEY"));
+        assert_eq!(servers["bare"].launch.inherit_env, InheritEnv::None);
+        assert_eq!(servers["plain"].launch, Launch::default());
+    }
+
+    #[test]
+    fn test_parse_env_file() {
+        let vars = parse_env_file("# comment\n\nexport A=1\nB = \"two words\"\nC='x=y'\nnot a var\n");
+        assert_eq!(vars.len(), 3);
+        assert_eq!(vars["A"], "1");
+        assert_eq!(vars["B"], "two words");
+        assert_eq!(vars["C"], "x=y");
+    }
+
     #[test]
     fn test_parse_servers_no_servers() {
         let json = json!({"otherKey": "value"});
@@ -4,6 +4,7 @@
 
 use crate::proxy::ProxyServer;
 use crate::sse::{extract_session_id, SseManager};
+use crate::streamable::StreamableManager;
 use serde_json::{json, Value};
 use std::fs;
 use std::path::PathBuf;
@@ -215,6 +216,8 @@ fn handle_get_servers() -> Vec<u8> {
         result.push(json!({
             "name": name,
             "command": srv.get("command").and_then(|v| v.as_str()).unwrap_or(""),
+            "url": srv.get("url").and_then(|v| v.as_str()).unwrap_or(""),
+            "headers": masked_headers(srv.get("headers")),
             "args": srv.get("args").unwrap_or(&json!([])),
             "env": srv.get("env").unwrap_or(&json!({})),
             "disabled": srv.get("disabled").and_then(|v| v.as_bool()).unwrap_or(false),
@@ -235,6 +238,53 @@ fn handle_get_servers() -> Vec<u8> {
     }))
 }
 
+/// Shown in place of a header's credential.
+const MASKED: &str = "****";
+
+/// A configured header value as the dashboard may show it: the auth scheme
+/// and a value that is just a `${...}` reference stay, literal credentials don't.
+fn mask_header(value: &str) -> String {
+    let (scheme, credential) = match value.split_once(' ') {
+        Some((scheme, credential)) => (Some(scheme), credential),
+        None => (None, value),
+    };
+    let is_reference = credential
+        .strip_prefix("${")
+        .and_then(|r| r.strip_suffix('}'))
+        .is_some_and(|inner| !inner.contains('}'));
+    let shown = if is_reference { credential } else { MASKED };
+    match scheme {
+        Some(scheme) => format!("{} {}", scheme, shown),
+        None => shown.to_string(),
+    }
+}
+
+fn masked_headers(headers: Option<&Value>) -> Value {
+    let masked = headers
+        .and_then(|h| h.as_object())
+        .into_iter()
+        .flatten()
+        .map(|(name, value)| (name.clone(), json!(mask_header(value.as_str().unwrap_or_default()))))
+        .collect();
+    Value::Object(masked)
+}
+
+/// Headers sent back by the edit form: a value still masked keeps what is stored.
+fn unmask_headers(incoming: &Value, stored: Option<&Value>) -> Value {
+    let Some(incoming) = incoming.as_object() else { return incoming.clone() };
+    let merged = incoming
+        .iter()
+        .map(|(name, value)| {
+            let previous = stored.and_then(|s| s.get(name)).and_then(|v| v.as_str());
+            match (value.as_str(), previous) {
+                (Some(value), Some(previous)) if value == mask_header(previous) => (name.clone(), json!(previous)),
+                _ => (name.clone(), value.clone()),
+            }
+        })
+        .collect();
+    Value::Object(merged)
+}
+
 fn handle_add_server(body: &str) -> Vec<u8> {
     let data: Value = match serde_json::from_str(body) {
         Ok(v) => v,
@@ -244,10 +294,11 @@ fn handle_add_server(body: &str) -> Vec<u8> {
         Some(n) => n.to_string(),
         None => return json_err(400, "Name required"),
     };
-    let command = match data.get("command").and_then(|v| v.as_str()) {
-        Some(c) => c.to_string(),
-        None => return json_err(400, "Command required"),
-    };
+    let command = data.get("command").and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let url = data.get("url").and_then(|v| v.as_str()).unwrap_or("").to_string();
+    if command.is_empty() && url.is_empty() {
+        return json_err(400, "Command or url required");
+    }
 
     let args = if let Some(s) = data.get("args").and_then(|v| v.as_str()) {
         Value::Array(
@@ -266,11 +317,19 @@ fn handle_add_server(body: &str) -> Vec<u8> {
     if config.get(key).is_none() {
         config[key] = json!({});
     }
-    config[key][&name] = json!({
-        "command": command,
-        "args": args,
-        "env": env
-    });
+    config[key][&name] = if command.is_empty() {
+        json!({
+            "url": url,
+            "headers": data.get("headers").cloned().unwrap_or(json!({})),
+            "env": env
+        })
+    } else {
+        json!({
+            "command": command,
+            "args": args,
+            "env": env
+        })
+    };
 
     if save_config(&config) {
         json_ok(json!({"ok": true, "message": "Server added"}))
@@ -324,6 +383,12 @@ fn handle_update_server(name: &str, body: &str) -> Vec<u8> {
     if let Some(env) = data.get("env") {
         srv["env"] = env.clone();
     }
+    if let Some(url) = data.get("url").and_then(|v| v.as_str()) {
+        srv["url"] = json!(url);
+    }
+    if let Some(headers) = data.get("headers") {
+        srv["headers"] = unmask_headers(headers, srv.get("headers"));
+    }
 
     if save_config(&config) {
         json_ok(json!({"ok": true}))
@@ -389,18 +454,40 @@ fn handle_get_settings() -> Vec<u8> {
     json_ok(settings)
 }
 
-async fn handle_get_metrics(proxy: Option<Arc<ProxyServer>>, sse: Option<Arc<SseManager>>) -> Vec<u8> {
+async fn handle_get_metrics(
+    proxy: Option<Arc<ProxyServer>>,
+    sse: Option<Arc<SseManager>>,
+    streamable: Option<Arc<StreamableManager>>,
+) -> Vec<u8> {
     if let Some(p) = proxy {
+        p.refresh_pool_metrics().await;
         let mut m = p.metrics.lock().await;
         if let Some(s) = sse {
             m.active_sse_sessions = s.session_count().await;
         }
+        if let Some(s) = streamable {
+            m.active_http_sessions = s.session_count().await;
+        }
         json_ok(json!(*m))
     } else {
         json_err(503, "Metrics not available in dashboard-only mode")
     }
 }
 
+fn handle_get_stderr(proxy: Option<Arc<ProxyServer>>, name: &str) -> Vec<u8> {
+    match proxy {
+        Some(p) => json_ok(json!({ "name": name, "lines": p.stderr_lines(name) })),
+        None => json_err(503, "Stderr not available in dashboard-only mode"),
+    }
+}
+
+fn handle_clear_cache(proxy: Option<Arc<ProxyServer>>, name: &str) -> Vec<u8> {
+    match proxy {
+        Some(p) => json_ok(json!({ "name": name, "cleared": p.invalidate_cache(name) })),
+        None => json_err(503, "Result cache not available in dashboard-only mode"),
+    }
+}
+
 fn handle_update_settings(body: &str) -> Vec<u8> {
     let data: Value = match serde_json::from_str(body) {
         Ok(v) => v,
@@ -499,11 +586,14 @@ async fn handle_repair_server(name: &str) -> Vec<u8> {
     }
 
     let srv = &servers[name];
-    let command = srv.get("command").and_then(|v| v.as_str()).unwrap_or("");
-    let args: Vec<String> = srv.get("args")
-        .and_then(|v| v.as_array())
-        .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
-        .unwrap_or_default();
+    if srv.get("command").is_none() && srv.get("url").is_some() {
+        return repair_remote_server(name).await;
+    }
+    let server = match crate::config::parse_server(name, srv) {
+        Some(s) => s,
+        None => return json_err(400, "Server has no command or url"),
+    };
+    let command = server.command.as_str();
 
     // Step 1: Check if command exists
     let cmd_check = tokio::process::Command::new("which")
@@ -549,20 +639,18 @@ async fn handle_repair_server(name: &str) -> Vec<u8> {
     }
 
     // Step 2: Try to start the server and get tools
-    let mut cmd = tokio::process::Command::new(command);
-    for arg in &args {
-        cmd.arg(arg);
-    }
-    
-    // Add env vars
-    if let Some(env_obj) = srv.get("env").and_then(|v| v.as_object()) {
-        for (k, v) in env_obj {
-            if let Some(val) = v.as_str() {
-                cmd.env(k, val);
-            }
+    let mut cmd = match crate::child::stdio_command(&server) {
+        Ok(c) => c,
+        Err(e) => {
+            return json_ok(json!({
+                "ok": false,
+                "step": "launch_config",
+                "error": e,
+                "suggestion": "Check the server's cwd and envFile",
+                "auto_fixable": false
+            }));
         }
-    }
-    
+    };
     cmd.stdin(std::process::Stdio::piped())
        .stdout(std::process::Stdio::piped())
        .stderr(std::process::Stdio::piped());
@@ -746,12 +834,55 @@ async fn handle_repair_server(name: &str) -> Vec<u8> {
     }
 }
 
+/// Remote servers have no command to check: just run the handshake through `generate`.
+async fn repair_remote_server(name: &str) -> Vec<u8> {
+    let gen_output = tokio::process::Command::new(binary_path())
+        .arg("generate")
+        .output()
+        .await;
+
+    match gen_output {
+        Ok(gen_out) => {
+            let gen_combined = format!("{}{}",
+                String::from_utf8_lossy(&gen_out.stderr),
+                String::from_utf8_lossy(&gen_out.stdout)
+            );
+            let server_line = gen_combined.lines()
+                .find(|l| l.contains(&format!("{} ... ", name)))
+                .unwrap_or("");
+            if server_line.is_empty() || server_line.contains("FAILED") {
+                let error_part = server_line.split("FAILED:").nth(1).unwrap_or("Unknown error").trim();
+                json_ok(json!({
+                    "ok": false,
+                    "step": "remote_failed",
+                    "error": format!("Remote handshake failed: {}", error_part),
+                    "suggestion": "Check the URL, headers and transport type (http or sse)",
+                    "auto_fixable": false
+                }))
+            } else {
+                json_ok(json!({
+                    "ok": true,
+                    "step": "repaired",
+                    "message": format!("Server '{}' is reachable and cache has been rebuilt", name)
+                }))
+            }
+        }
+        Err(e) => json_ok(json!({
+            "ok": false,
+            "step": "generate_error",
+            "error": format!("Cache rebuild failed: {}", e),
+            "auto_fixable": false
+        }))
+    }
+}
+
 // ─── Router ──────────────────────────────────────────────────
 
 async fn route(
     req: &HttpRequest,
     proxy: Option<Arc<ProxyServer>>,
     sse: Option<Arc<SseManager>>,
+    streamable: Option<Arc<StreamableManager>>,
 ) -> Vec<u8> {
     let path = req.path.split('?').next().unwrap_or(&req.path);
 
@@ -764,20 +895,23 @@ async fn route(
         ("GET", "/api/servers") => handle_get_servers(),
         ("POST", "/api/servers") => handle_add_server(&req.body),
         ("GET", "/api/settings") => handle_get_settings(),
-        ("GET", "/api/metrics") => handle_get_metrics(proxy, sse).await,
+        ("GET", "/api/metrics") => handle_get_metrics(proxy, sse, streamable).await,
         ("PUT", "/api/settings") => handle_update_settings(&req.body),
         ("POST", "/api/generate") => handle_generate().await,
         _ => {
-            if path.starts_with("/api/servers/") {
-                let rest = &path["/api/servers/".len()..];
-                if rest.ends_with("/toggle") {
-                    let name = &rest[..rest.len() - "/toggle".len()];
+            if let Some(rest) = path.strip_prefix("/api/servers/") {
+                if let Some(name) = rest.strip_suffix("/toggle") {
                     let decoded = urldecode(name);
                     handle_toggle_server(&decoded, &req.body)
-                } else if rest.ends_with("/repair") {
-                    let name = &rest[..rest.len() - "/repair".len()];
+                } else if let Some(name) = rest.strip_suffix("/repair") {
                     let decoded = urldecode(name);
                     handle_repair_server(&decoded).await
+                } else if let Some(name) = rest.strip_suffix("/stderr") {
+                    let decoded = urldecode(name);
+                    handle_get_stderr(proxy, &decoded)
+                } else if let Some(name) = rest.strip_suffix("/cache").filter(|_| req.method == "DELETE") {
+                    let decoded = urldecode(name);
+                    handle_clear_cache(proxy, &decoded)
                 } else {
                     let decoded = urldecode(rest);
                     match &req.method[..] {
@@ -815,17 +949,24 @@ fn urldecode(s: &str) -> String {
 
 /// Start dashboard only (no SSE, no proxy). For `McpHub dashboard` command.
 pub async fn start_dashboard() {
-    start_http(None, None, true).await;
+    start_http(None, None, None, true).await;
 }
 
-/// Start full server: dashboard + SSE transport. For `McpHub serve` and default mode.
+/// Start full server: dashboard + SSE + Streamable HTTP transports. For `McpHub serve` and default mode.
 pub async fn start_server(proxy: Arc<ProxyServer>) {
-    start_http(Some(proxy), Some(Arc::new(SseManager::new())), false).await;
+    start_http(
+        Some(proxy),
+        Some(Arc::new(SseManager::new())),
+        Some(Arc::new(StreamableManager::new())),
+        false,
+    )
+    .await;
 }
 
 async fn start_http(
     proxy: Option<Arc<ProxyServer>>,
     sse: Option<Arc<SseManager>>,
+    streamable: Option<Arc<StreamableManager>>,
     open_browser: bool,
 ) {
     let addr = "127.0.0.1:24680";
@@ -841,6 +982,7 @@ async fn start_http(
     if proxy.is_some() {
         eprintln!("[McpHub][HTTP] Server ready on http://{}", addr);
         eprintln!("[McpHub][SSE]  Cursor endpoint: http://{}/sse", addr);
+        eprintln!("[McpHub][HTTP] Streamable HTTP endpoint: http://{}/mcp", addr);
     } else {
         eprintln!("[dashboard] Running on http://{}", addr);
     }
@@ -868,9 +1010,10 @@ async fn start_http(
 
         let proxy_clone = proxy.clone();
         let sse_clone = sse.clone();
+        let streamable_clone = streamable.clone();
 
         tokio::spawn(async move {
-            handle_connection(stream, proxy_clone, sse_clone).await;
+            handle_connection(stream, proxy_clone, sse_clone, streamable_clone).await;
         });
     }
 }
@@ -879,6 +1022,7 @@ async fn handle_connection(
     mut stream: tokio::net::TcpStream,
     proxy: Option<Arc<ProxyServer>>,
     sse: Option<Arc<SseManager>>,
+    streamable: Option<Arc<StreamableManager>>,
 ) {
     // Add CORS OPTIONS handler
     let mut buf = vec![0u8; 65536];
@@ -892,7 +1036,7 @@ async fn handle_connection(
 
     // Check if it's an OPTIONS request early
     if total_read >= 7 && &buf[..7] == b"OPTIONS" {
-        let resp = b"HTTP/1.1 204 No Content\r\nAccess-Control-Allow-Origin: *\r\nAccess-Control-Allow-Methods: GET, POST, OPTIONS\r\nAccess-Control-Allow-Headers: Content-Type, Authorization\r\nAccess-Control-Max-Age: 86400\r\nContent-Length: 0\r\n\r\n";
+        let resp = b"HTTP/1.1 204 No Content\r\nAccess-Control-Allow-Origin: *\r\nAccess-Control-Allow-Methods: GET, POST, DELETE, OPTIONS\r\nAccess-Control-Allow-Headers: Content-Type, Authorization, Mcp-Session-Id, Mcp-Protocol-Version\r\nAccess-Control-Expose-Headers: Mcp-Session-Id\r\nAccess-Control-Max-Age: 86400\r\nContent-Length: 0\r\n\r\n";
         let _ = stream.write_all(resp).await;
         return;
     }
@@ -994,6 +1138,39 @@ async fn handle_connection(
         return;
     }
 
+    // Streamable HTTP endpoint: POST messages, GET server stream, DELETE session
+    if path == "/mcp" {
+        let auth = req.headers.get("authorization").map(|s| s.as_str()).unwrap_or("");
+        if auth != expected_auth {
+            let resp = json_err(401, "Unauthorized");
+            let _ = stream.write_all(&resp).await;
+            let _ = stream.shutdown().await;
+            return;
+        }
+
+        let (proxy_ref, http_mgr) = match (&proxy, &streamable) {
+            (Some(p), Some(s)) => (p, s),
+            _ => {
+                let resp = json_err(503, "Streamable HTTP not available in dashboard-only mode");
+                let _ = stream.write_all(&resp).await;
+                let _ = stream.shutdown().await;
+                return;
+            }
+        };
+
+        match &req.method[..] {
+            "POST" => http_mgr.handle_post(stream, &req.headers, &req.body, proxy_ref).await,
+            "GET" => http_mgr.handle_get(stream, &req.headers).await,
+            "DELETE" => http_mgr.handle_delete(stream, &req.headers).await,
+            _ => {
+                let resp = json_err(405, "Method not allowed");
+                let _ = stream.write_all(&resp).await;
+                let _ = stream.shutdown().await;
+            }
+        }
+        return;
+    }
+
     if path == "/api/logs-stream" && req.method == "GET" {
         let headers = "HTTP/1.1 200 OK\r\n\
              Content-Type: text/event-stream\r\n\
@@ -1028,7 +1205,7 @@ async fn handle_connection(
                         let line_trim = line.trim();
                         if !line_trim.is_empty() {
                             let json_msg = serde_json::json!({ "line": line_trim });
-                            let event = format!("event: message\ndata: {}\n\n", json_msg.to_string());
+                            let event = format!("event: message\ndata: {}\n\n", json_msg);
                             if stream.write_all(event.as_bytes()).await.is_err() {
                                 break;
                             }
@@ -1042,7 +1219,7 @@ async fn handle_connection(
     }
 
     // Normal dashboard routes
-    let response = route(&req, proxy, sse).await;
+    let response = route(&req, proxy, sse, streamable).await;
     let _ = stream.write_all(&response).await;
     let _ = stream.shutdown().await;
 }
@@ -1050,3 +1227,22 @@ async fn handle_connection(
 // ─── Embedded HTML ───────────────────────────────────────────
 
 const DASHBOARD_HTML: &str = include_str!("../static/dashboard.html");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_masking() {
+        assert_eq!(mask_header("Bearer ghp_abc123"), "Bearer ****");
+        assert_eq!(mask_header("sk-live-1234"), "****");
+        assert_eq!(mask_header("Bearer ${secret:github}"), "Bearer ${secret:github}");
+        assert_eq!(mask_header("${env:API_KEY}"), "${env:API_KEY}");
+        assert_eq!(mask_header("${env:A}${env:B}"), "****");
+
+        let stored = json!({ "Authorization": "Bearer ghp_abc123", "X-Team": "core" });
+        let edited = json!({ "Authorization": "Bearer ****", "X-Team": "infra", "X-New": "1" });
+        let merged = unmask_headers(&edited, Some(&stored));
+        assert_eq!(merged, json!({ "Authorization": "Bearer ghp_abc123", "X-Team": "infra", "X-New": "1" }));
+    }
+}
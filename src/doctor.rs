@@ -47,7 +47,12 @@ pub fn run() {
     
     for (name, srv) in &config.servers {
         print!("  {} ... ", name);
-        
+
+        if srv.is_remote() {
+            println!("✓ Remote endpoint {}", srv.endpoint());
+            continue;
+        }
+
         // Check command exists
         let output = Command::new("which").arg(&srv.command).output();
         let cmd_exists = output.map(|o| o.status.success()).unwrap_or(false);
@@ -65,7 +70,29 @@ pub fn run() {
         println!();
     }
 
-    // 6. Disk usage
+    // 6. Leftover server processes
+    let leftovers = crate::procgroup::leftovers();
+    if leftovers.is_empty() {
+        println!("\n✓ Processes: no leftover server processes");
+    } else {
+        println!("\n✗ Processes: {} leftover process group(s) of stopped servers:", leftovers.len());
+        for group in &leftovers {
+            println!("  {} (process group {}) — stop with: kill -TERM -- -{}", group.server, group.pgid, group.pgid);
+        }
+    }
+
+    // 7. Sandbox support
+    match crate::sandbox::landlock_abi() {
+        Some(abi) => println!("\n✓ Sandbox paths: Landlock ABI v{}", abi),
+        None => println!("\n! Sandbox paths: Landlock not supported by this kernel (servers with readOnly/readWrite won't start)"),
+    }
+    if crate::sandbox::seccomp_supported() {
+        println!("✓ Sandbox network: seccomp filters available");
+    } else {
+        println!("! Sandbox network: seccomp filters not available (servers with \"network\": false won't start)");
+    }
+
+    // 8. Disk usage
     let mut total_size = 0;
     if let Ok(entries) = std::fs::read_dir(mcphub_dir()) {
         for entry in entries.flatten() {
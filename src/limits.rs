@@ -0,0 +1,190 @@
+//! Resource limits for stdio servers (`"limits"` in config), enforced on Linux.
+//! Each process is held by rlimits set in the child before exec, so a runaway
+//! allocation or fork loop fails at once: `nofile`, `memoryMb` (RLIMIT_DATA),
+//! `cpuSecs` (RLIMIT_CPU) and `maxProcs` (RLIMIT_NPROC). Memory, CPU time and
+//! process count are also totals over the server's process group, so `npx` and
+//! the node process it starts count together; those and the wall clock are
+//! sampled from /proc once a second and the group is killed when one is exceeded.
+
+use std::time::Duration;
+
+use crate::config::Limits;
+
+/// How often a limited server's usage is sampled.
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Every reason produced by `exceeded` starts with this.
+const EXCEEDED_PREFIX: &str = "Exceeded ";
+
+/// Totals over one process group.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Usage {
+    pub rss_bytes: u64,
+    pub cpu_secs: f64,
+    pub procs: u64,
+}
+
+/// The first limit `usage` breaks, worded as the reason the server was killed.
+pub fn exceeded(limits: &Limits, usage: &Usage, age: Duration) -> Option<String> {
+    if let Some(mb) = limits.memory_mb {
+        if usage.rss_bytes > mb * 1024 * 1024 {
+            return Some(format!("{}memory limit ({} MB, was using {} MB)", EXCEEDED_PREFIX, mb, usage.rss_bytes / 1024 / 1024));
+        }
+    }
+    if let Some(secs) = limits.cpu_secs {
+        if usage.cpu_secs > secs as f64 {
+            return Some(format!("{}CPU limit ({}s)", EXCEEDED_PREFIX, secs));
+        }
+    }
+    if let Some(max) = limits.max_procs {
+        if usage.procs > max {
+            return Some(format!("{}process limit ({}, had {})", EXCEEDED_PREFIX, max, usage.procs));
+        }
+    }
+    if let Some(secs) = limits.wall_clock_secs {
+        if age.as_secs() >= secs {
+            return Some(format!("{}wall-clock limit ({}s)", EXCEEDED_PREFIX, secs));
+        }
+    }
+    None
+}
+
+/// Whether a down reason is a limit kill (see `exceeded`).
+pub fn is_exceeded(reason: &str) -> bool {
+    reason.starts_with(EXCEEDED_PREFIX)
+}
+
+/// Clock ticks per second in /proc/<pid>/stat (USER_HZ, 100 on every Linux ABI).
+#[cfg(target_os = "linux")]
+const USER_HZ: f64 = 100.0;
+
+/// Usage of every process in group `pgid`, or None once the group is gone.
+#[cfg(target_os = "linux")]
+pub fn group_usage(pgid: u32) -> Option<Usage> {
+    let mut usage = Usage::default();
+    for entry in std::fs::read_dir("/proc").ok()?.flatten() {
+        let pid: u32 = match entry.file_name().to_str().and_then(|n| n.parse().ok()) {
+            Some(pid) => pid,
+            None => continue,
+        };
+        let stat = match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+            Ok(stat) => stat,
+            Err(_) => continue,
+        };
+        // The command name may contain spaces; fields resume after its closing paren,
+        // starting at field 3 (state): pgrp is field 5, utime..cstime are 14..=17.
+        let fields: Vec<&str> = match stat.rfind(')') {
+            Some(end) => stat[end + 1..].split_whitespace().collect(),
+            None => continue,
+        };
+        if fields.get(2).and_then(|f| f.parse::<u32>().ok()) != Some(pgid) {
+            continue;
+        }
+        usage.procs += 1;
+        let ticks: u64 = fields.get(11..15).unwrap_or_default().iter().filter_map(|f| f.parse::<u64>().ok()).sum();
+        usage.cpu_secs += ticks as f64 / USER_HZ;
+        usage.rss_bytes += resident_bytes(pid);
+    }
+    (usage.procs > 0).then_some(usage)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn group_usage(_pgid: u32) -> Option<Usage> {
+    None
+}
+
+/// `VmRSS` of one process (absent for zombies and kernel threads).
+#[cfg(target_os = "linux")]
+fn resident_bytes(pid: u32) -> u64 {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).unwrap_or_default();
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|v| v.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+        .map(|kb| kb * 1024)
+        .unwrap_or(0)
+}
+
+/// Set the rlimits among `limits` in the child before it execs. Limits can
+/// only be lowered, never raised past the inherited hard limit.
+///
+/// RLIMIT_DATA rather than RLIMIT_AS: V8 and the JVM reserve far more address
+/// space than they use and would not start under an address-space cap.
+/// RLIMIT_NPROC counts every process of the user, so `maxProcs` is added to
+/// the number the user already runs; the group total is still polled.
+#[cfg(target_os = "linux")]
+pub fn apply_rlimits(cmd: &mut tokio::process::Command, limits: &Limits) {
+    let rlim = |n: u64| n as libc::rlim_t;
+    let wanted = [
+        (libc::RLIMIT_NOFILE, limits.nofile.map(|n| (rlim(n), rlim(n)))),
+        (libc::RLIMIT_DATA, limits.memory_mb.map(|mb| (rlim(mb * 1024 * 1024), rlim(mb * 1024 * 1024)))),
+        // SIGXCPU at the limit, SIGKILL a second later if that is ignored
+        (libc::RLIMIT_CPU, limits.cpu_secs.map(|secs| (rlim(secs), rlim(secs + 1)))),
+        (libc::RLIMIT_NPROC, limits.max_procs.map(|max| user_processes() + max).map(|n| (rlim(n), rlim(n)))),
+    ];
+    if wanted.iter().all(|(_, limit)| limit.is_none()) {
+        return;
+    }
+    // SAFETY: the closure runs in the forked child and only calls getrlimit and
+    // setrlimit, which are async-signal-safe, on the array copied into it.
+    unsafe {
+        cmd.pre_exec(move || {
+            for (resource, wanted) in wanted {
+                let Some((soft, hard)) = wanted else { continue };
+                let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+                if libc::getrlimit(resource, &mut limit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                limit.rlim_max = limit.rlim_max.min(hard);
+                limit.rlim_cur = limit.rlim_max.min(soft);
+                if libc::setrlimit(resource, &limit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Processes owned by our real user, as RLIMIT_NPROC counts them.
+#[cfg(target_os = "linux")]
+fn user_processes() -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    // SAFETY: getuid takes no arguments and cannot fail.
+    let uid = unsafe { libc::getuid() };
+    let Ok(entries) = std::fs::read_dir("/proc") else { return 0 };
+    entries
+        .flatten()
+        .filter(|e| e.file_name().to_str().is_some_and(|n| n.bytes().all(|b| b.is_ascii_digit())))
+        .filter(|e| e.metadata().is_ok_and(|m| m.uid() == uid))
+        .count() as u64
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn apply_rlimits(_cmd: &mut tokio::process::Command, _limits: &Limits) {}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rlimits_apply_to_child() {
+        let limits = Limits { memory_mb: Some(512), cpu_secs: Some(30), nofile: Some(64), max_procs: Some(8), wall_clock_secs: None };
+        let mut cmd = tokio::process::Command::new("cat");
+        cmd.arg("/proc/self/limits");
+        apply_rlimits(&mut cmd, &limits);
+        let output = cmd.output().await.unwrap();
+        let table = String::from_utf8_lossy(&output.stdout);
+        // "Max cpu time   30   31   seconds": soft and hard limit
+        let row = |name: &str| -> (String, String) {
+            let line = table.lines().find(|l| l.starts_with(name)).unwrap();
+            let cols: Vec<&str> = line[name.len()..].split_whitespace().collect();
+            (cols[0].to_string(), cols[1].to_string())
+        };
+        assert_eq!(row("Max cpu time"), ("30".into(), "31".into()));
+        assert_eq!(row("Max data size"), ("536870912".into(), "536870912".into()));
+        assert_eq!(row("Max open files"), ("64".into(), "64".into()));
+        let (soft, _) = row("Max processes");
+        assert!(soft.parse::<u64>().unwrap() >= 8, "{}", table);
+    }
+}
@@ -60,4 +60,49 @@ pub fn run(server_filter: Option<&str>, level_filter: Option<&str>) {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Print a server's buffered stderr, fetched from the running daemon.
+pub async fn run_stderr(server: Option<&str>) {
+    let server = match server {
+        Some(s) => s,
+        None => {
+            eprintln!("Usage: McpHub logs --server <name> --stderr");
+            return;
+        }
+    };
+
+    // The name is one path segment, whatever characters it holds
+    let mut url = reqwest::Url::parse("http://127.0.0.1:24680/api/servers").expect("valid base URL");
+    url.path_segments_mut().expect("base URL has a path").push(server).push("stderr");
+    let resp = match reqwest::get(url).await {
+        Ok(r) => r,
+        Err(_) => {
+            eprintln!("McpHub is not running on port 24680. Start it with 'McpHub serve'.");
+            return;
+        }
+    };
+    let text = resp.text().await.unwrap_or_default();
+    let body: serde_json::Value = match serde_json::from_str(&text) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Unexpected response from McpHub: {}", e);
+            return;
+        }
+    };
+    if let Some(error) = body.get("error").and_then(|v| v.as_str()) {
+        eprintln!("{}", error);
+        return;
+    }
+
+    let lines = body.get("lines").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+    if lines.is_empty() {
+        println!("No stderr captured for '{}' (it may not have been started yet).", server);
+        return;
+    }
+    for line in lines {
+        if let Some(line) = line.as_str() {
+            println!("{}", line);
+        }
+    }
+}
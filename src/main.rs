@@ -1,18 +1,31 @@
 mod add;
 mod benchmark;
+mod breaker;
 mod cache;
 pub mod child;
+mod coalesce;
 mod config;
 mod dashboard;
 mod doctor;
 mod export;
 mod health;
 mod install;
+mod limits;
 mod logs;
+mod offload;
+mod peer;
+mod procgroup;
 mod protocol;
 mod proxy;
+mod remote;
+mod result_cache;
+mod sandbox;
 mod search;
+mod secrets;
 mod sse;
+mod stderr;
+mod streamable;
+mod throttle;
 mod update;
 
 use config::auto_detect;
@@ -28,7 +41,7 @@ McpHub v{VERSION} — Fastest MCP proxy with BM25 tool discovery
 
 USAGE:
   McpHub              Start proxy (stdio + HTTP server on :24680)
-  McpHub serve        Start HTTP-only server (SSE transport, no stdio)
+  McpHub serve        Start HTTP-only server (SSE + Streamable HTTP, no stdio)
   McpHub generate     Start all servers, index tools, save cache
   McpHub dashboard    Open web dashboard on http://127.0.0.1:24680
   McpHub install      Register McpHub to auto-start at login
@@ -36,10 +49,12 @@ USAGE:
   McpHub status       Show detected servers, cache, and health config
   McpHub doctor       Run full diagnostic of the installation
   McpHub logs         Tail daemon logs in real time
+                      (--server x --stderr: recent stderr of server x)
   McpHub add          Interactively add a new server
   McpHub benchmark    Measure start and ping times for servers
   McpHub export       Export configuration to stdout
   McpHub import       Import configuration from a file
+  McpHub secret       Manage stored secrets (set/get/list/rm <name>)
   McpHub search "q"   Test BM25 search
   McpHub update       Self-update to the latest version on GitHub
   McpHub version      Show version
@@ -53,6 +68,7 @@ TRANSPORT MODES:
   Serve (HTTP only, recommended):
     Cursor config: {{"mcpServers": {{"McpHub": {{"url": "http://127.0.0.1:24680/sse", "headers": {{"Authorization": "Bearer <token>"}}}}}}}}
     Run 'McpHub install' to auto-start, then configure Cursor with URL and token.
+    Clients supporting Streamable HTTP can use "http://127.0.0.1:24680/mcp" instead.
     Survives Cursor restarts. Single process for everything.
 
 FIRST TIME SETUP:
@@ -89,8 +105,7 @@ fn cmd_status() {
     names.sort();
     for name in names {
         let s = &config.servers[name];
-        let args = s.args.join(" ");
-        println!("  {} → {} {}", name, s.command, args);
+        println!("  {} → {}", name, s.endpoint());
     }
 }
 
@@ -228,15 +243,22 @@ async fn main() {
         Some("logs") => {
             let mut server = None;
             let mut level = None;
+            let mut stderr = false;
             let mut iter = args.iter().skip(2);
             while let Some(arg) = iter.next() {
                 if arg == "--server" {
                     server = iter.next().map(|s| s.as_str());
                 } else if arg == "--level" {
                     level = iter.next().map(|s| s.as_str());
+                } else if arg == "--stderr" {
+                    stderr = true;
                 }
             }
-            logs::run(server, level);
+            if stderr {
+                logs::run_stderr(server).await;
+            } else {
+                logs::run(server, level);
+            }
         }
         Some("add") => add::run().await,
         Some("benchmark") => benchmark::run().await,
@@ -248,6 +270,7 @@ async fn main() {
                 eprintln!("Usage: McpHub import <file>");
             }
         }
+        Some("secret") => secrets::run(&args[2..]),
         Some("generate") => cmd_generate().await,
         Some("dashboard") | Some("ui") | Some("web") => dashboard::start_dashboard().await,
         Some("install") => install::install(),
@@ -0,0 +1,180 @@
+//! Oversized tool results (`settings.largeResults`, off by default). A 2 MB
+//! dump returned by one `execute` would fill the model's context, so its text
+//! is kept here instead and the client gets a preview plus a handle. The
+//! `fetch_result` meta-tool pages through the rest; handles expire after `ttl`
+//! seconds.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_json::{json, Value};
+
+use crate::config::LargeResults;
+
+/// `_meta` key of a cut-down result: `{"handle", "totalChars", "previewChars", "expiresInSecs"}`.
+pub const META_KEY: &str = "mcphub/offloaded";
+
+/// Characters `fetch_result` returns when no `length` is given.
+const DEFAULT_PAGE_CHARS: usize = 10_000;
+
+/// Beyond this many bytes of stored text, the oldest results are dropped early.
+const MAX_STORED_BYTES: usize = 64 * 1024 * 1024;
+
+struct Stored {
+    text: String,
+    total_chars: usize,
+    stored: Instant,
+    ttl: Duration,
+}
+
+impl Stored {
+    fn expired(&self) -> bool {
+        self.stored.elapsed() >= self.ttl
+    }
+}
+
+#[derive(Default)]
+pub struct ResultStore {
+    map: Mutex<HashMap<String, Stored>>,
+}
+
+/// The text of a tool result: text blocks and embedded text resources, or
+/// `structuredContent` when there are none.
+fn result_text(result: &Value) -> String {
+    let mut parts = Vec::new();
+    for block in result.get("content").and_then(|c| c.as_array()).into_iter().flatten() {
+        let text = match block.get("type").and_then(|t| t.as_str()) {
+            Some("text") => block.get("text"),
+            Some("resource") => block.pointer("/resource/text"),
+            _ => None,
+        };
+        if let Some(text) = text.and_then(|t| t.as_str()) {
+            parts.push(text);
+        }
+    }
+    match (parts.is_empty(), result.get("structuredContent")) {
+        (true, Some(structured)) => serde_json::to_string_pretty(structured).unwrap_or_default(),
+        _ => parts.join("\n"),
+    }
+}
+
+fn is_text_block(block: &Value) -> bool {
+    match block.get("type").and_then(|t| t.as_str()) {
+        Some("text") => true,
+        Some("resource") => block.pointer("/resource/text").is_some(),
+        _ => false,
+    }
+}
+
+fn new_handle() -> String {
+    let mut bytes = [0u8; 8];
+    let _ = SystemRandom::new().fill(&mut bytes);
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("res_{}", hex)
+}
+
+impl ResultStore {
+    /// If the text of `result` is over budget, keep it and cut `result` down
+    /// to a preview with a handle. Images and other non-text blocks stay.
+    pub fn offload(&self, result: &mut Value, config: &LargeResults) -> Option<String> {
+        if !config.enabled {
+            return None;
+        }
+        let text = result_text(result);
+        let total_chars = text.chars().count();
+        if total_chars <= config.max_chars {
+            return None;
+        }
+
+        let handle = new_handle();
+        let preview: String = text.chars().take(config.preview_chars).collect();
+        let preview_chars = preview.chars().count();
+        let note = format!(
+            "\n\n[Result truncated by McpHub: {} characters, showing the first {}. Call fetch_result with {{\"handle\": \"{}\", \"offset\": {}}} to read on. The handle expires in {}s.]",
+            total_chars, preview_chars, handle, preview_chars, config.ttl_secs
+        );
+        let obj = result.as_object_mut()?;
+        let mut content = vec![json!({ "type": "text", "text": preview + &note })];
+        if let Some(blocks) = obj.get("content").and_then(|c| c.as_array()) {
+            content.extend(blocks.iter().filter(|b| !is_text_block(b)).cloned());
+        }
+        obj.insert("content".into(), Value::Array(content));
+        obj.remove("structuredContent");
+        let meta = obj.entry("_meta").or_insert_with(|| json!({}));
+        if let Some(meta) = meta.as_object_mut() {
+            let mark = json!({
+                "handle": handle,
+                "totalChars": total_chars,
+                "previewChars": preview_chars,
+                "expiresInSecs": config.ttl_secs,
+            });
+            meta.insert(META_KEY.into(), mark);
+        }
+
+        let mut map = self.map.lock().unwrap_or_else(|e| e.into_inner());
+        map.retain(|_, s| !s.expired());
+        let mut stored_bytes: usize = map.values().map(|s| s.text.len()).sum();
+        while stored_bytes + text.len() > MAX_STORED_BYTES {
+            let Some(oldest) = map.iter().min_by_key(|(_, s)| s.stored).map(|(k, _)| k.clone()) else { break };
+            stored_bytes -= map.remove(&oldest).map(|s| s.text.len()).unwrap_or(0);
+        }
+        let ttl = Duration::from_secs(config.ttl_secs);
+        map.insert(handle.clone(), Stored { text, total_chars, stored: Instant::now(), ttl });
+        Some(handle)
+    }
+
+    /// `fetch_result`: up to `length` characters (at most `maxChars`) from `offset`.
+    pub fn fetch(&self, handle: &str, offset: usize, length: Option<usize>, config: &LargeResults) -> Result<Value, String> {
+        let map = self.map.lock().unwrap_or_else(|e| e.into_inner());
+        let stored = map
+            .get(handle)
+            .filter(|s| !s.expired())
+            .ok_or_else(|| format!("Unknown or expired result handle: {}. Run the tool again to get a new one.", handle))?;
+        if offset >= stored.total_chars {
+            return Err(format!("Offset {} is past the end of the result ({} characters)", offset, stored.total_chars));
+        }
+        let length = length.unwrap_or(DEFAULT_PAGE_CHARS).clamp(1, config.max_chars);
+        let page: String = stored.text.chars().skip(offset).take(length).collect();
+        let end = offset + page.chars().count();
+        let footer = match end < stored.total_chars {
+            true => format!("[Characters {}-{} of {}. Next: offset {}]", offset, end, stored.total_chars, end),
+            false => format!("[Characters {}-{} of {}. End of result]", offset, end, stored.total_chars),
+        };
+        Ok(json!({
+            "content": [{ "type": "text", "text": page }, { "type": "text", "text": footer }],
+            "_meta": { META_KEY: { "handle": handle, "offset": offset, "end": end, "totalChars": stored.total_chars } }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offload_and_page() {
+        let store = ResultStore::default();
+        let config = LargeResults { enabled: true, max_chars: 10, preview_chars: 4, ttl_secs: 60 };
+
+        let mut small = json!({ "content": [{ "type": "text", "text": "short" }] });
+        assert!(store.offload(&mut small, &config).is_none());
+
+        let image = json!({ "type": "image", "data": "AAAA", "mimeType": "image/png" });
+        let mut large = json!({ "content": [{ "type": "text", "text": "ünïcode dump" }, image] });
+        let handle = store.offload(&mut large, &config).unwrap();
+        let text = large["content"][0]["text"].as_str().unwrap();
+        assert!(text.starts_with("ünïc\n\n[Result truncated"), "{}", text);
+        assert_eq!(large["content"][1], image);
+        assert_eq!(large["_meta"][META_KEY]["totalChars"], 12);
+
+        let page = store.fetch(&handle, 4, Some(5), &config).unwrap();
+        assert_eq!(page["content"][0]["text"], "ode d");
+        assert_eq!(page["content"][1]["text"], "[Characters 4-9 of 12. Next: offset 9]");
+        let last = store.fetch(&handle, 9, None, &config).unwrap();
+        assert_eq!(last["content"][0]["text"], "ump");
+        assert!(store.fetch(&handle, 12, None, &config).is_err());
+        assert!(store.fetch("res_missing", 0, None, &config).is_err());
+    }
+}
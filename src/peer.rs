@@ -0,0 +1,206 @@
+//! Downstream client handles. Each stdio, SSE or Streamable HTTP session owns a
+//! `ClientPeer` so upstream servers can send requests (sampling, roots, elicitation)
+//! back to the client that made the originating call, and get the answer.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use serde_json::Value;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, oneshot, watch, Mutex};
+
+/// How long a client may take to answer a relayed request.
+/// Sampling and elicitation usually wait on a human, so this is generous.
+const CLIENT_REQUEST_TIMEOUT_SECS: u64 = 300;
+
+/// Source of `ClientPeer::id`.
+static NEXT_PEER_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug)]
+enum Outbound {
+    /// Newline-delimited JSON on our own stdout.
+    Stdio(Mutex<tokio::io::Stdout>),
+    /// `event: message` frames on an SSE stream, when one is open.
+    Sse(std::sync::Mutex<Option<mpsc::Sender<String>>>),
+}
+
+#[derive(Debug)]
+pub struct ClientPeer {
+    id: u64,
+    outbound: Outbound,
+    next_id: AtomicU64,
+    pending: Mutex<HashMap<u64, oneshot::Sender<Value>>>,
+    /// Negotiated on `initialize`; results are reshaped for older clients.
+    protocol_version: std::sync::Mutex<&'static str>,
+    /// Never sent on; receivers see it close when the peer is dropped.
+    closed: watch::Sender<()>,
+    /// SSE response streams of POSTs still being answered, by client request id.
+    /// Messages about one of those requests go out on its own stream.
+    request_streams: std::sync::Mutex<HashMap<String, mpsc::Sender<String>>>,
+}
+
+impl ClientPeer {
+    pub fn stdio() -> Self {
+        Self::with(Outbound::Stdio(Mutex::new(tokio::io::stdout())))
+    }
+
+    /// A client reached through an SSE stream. `tx` may be attached later.
+    pub fn sse(tx: Option<mpsc::Sender<String>>) -> Self {
+        Self::with(Outbound::Sse(std::sync::Mutex::new(tx)))
+    }
+
+    fn with(outbound: Outbound) -> Self {
+        Self {
+            id: NEXT_PEER_ID.fetch_add(1, Ordering::Relaxed),
+            outbound,
+            next_id: AtomicU64::new(1),
+            pending: Mutex::new(HashMap::new()),
+            protocol_version: std::sync::Mutex::new(crate::protocol::LATEST_PROTOCOL_VERSION),
+            closed: watch::channel(()).0,
+            request_streams: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Unique for the life of the process; identifies the session in lookup tables.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// `changed()` on the receiver returns an error once the session has ended
+    /// and its peer has been dropped.
+    pub fn closed(&self) -> watch::Receiver<()> {
+        self.closed.subscribe()
+    }
+
+    pub fn protocol_version(&self) -> &'static str {
+        self.protocol_version.lock().map(|v| *v).unwrap_or(crate::protocol::LATEST_PROTOCOL_VERSION)
+    }
+
+    pub fn set_protocol_version(&self, version: &'static str) {
+        if let Ok(mut v) = self.protocol_version.lock() {
+            *v = version;
+        }
+    }
+
+    /// Attach or detach the stream server-initiated messages go out on.
+    pub fn set_stream(&self, tx: Option<mpsc::Sender<String>>) {
+        if let Outbound::Sse(slot) = &self.outbound {
+            if let Ok(mut slot) = slot.lock() {
+                *slot = tx;
+            }
+        }
+    }
+
+    /// Carry messages about `request_id` on the stream answering it, until detached.
+    pub fn attach_request_stream(&self, request_id: &Value, tx: mpsc::Sender<String>) {
+        if let Ok(mut streams) = self.request_streams.lock() {
+            streams.insert(request_id.to_string(), tx);
+        }
+    }
+
+    pub fn detach_request_stream(&self, request_id: &Value) {
+        if let Ok(mut streams) = self.request_streams.lock() {
+            streams.remove(&request_id.to_string());
+        }
+    }
+
+    /// Write one JSON-RPC message to the client.
+    pub async fn send(&self, message: &Value) -> Result<(), String> {
+        self.send_for(None, message).await
+    }
+
+    /// Write a message about the client's request `request_id`: on that
+    /// request's response stream while it is open, else as `send` would.
+    pub async fn send_for(&self, request_id: Option<&Value>, message: &Value) -> Result<(), String> {
+        let json = serde_json::to_string(message).map_err(|e| e.to_string())?;
+        let request_stream = request_id.and_then(|id| {
+            self.request_streams.lock().ok().and_then(|streams| streams.get(&id.to_string()).cloned())
+        });
+        if let Some(tx) = request_stream {
+            return tx
+                .send(format!("event: message\ndata: {}\n\n", json))
+                .await
+                .map_err(|_| "Client stream closed".to_string());
+        }
+        match &self.outbound {
+            Outbound::Stdio(stdout) => {
+                let mut stdout = stdout.lock().await;
+                let line = format!("{}\n", json);
+                stdout.write_all(line.as_bytes()).await.map_err(|e| format!("Write error: {}", e))?;
+                stdout.flush().await.map_err(|e| format!("Flush error: {}", e))
+            }
+            Outbound::Sse(slot) => {
+                let tx = slot.lock().ok().and_then(|s| s.clone()).ok_or("Client has no open stream")?;
+                tx.send(format!("event: message\ndata: {}\n\n", json))
+                    .await
+                    .map_err(|_| "Client stream closed".to_string())
+            }
+        }
+    }
+
+    /// Send a request to the client and wait for its response message. It is
+    /// sent as part of the client's request `on`, if it belongs to one.
+    pub async fn request(&self, on: Option<&Value>, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().await.insert(id, tx);
+
+        let message = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+        if let Err(e) = self.send_for(on, &message).await {
+            self.pending.lock().await.remove(&id);
+            return Err(e);
+        }
+
+        let timeout = std::time::Duration::from_secs(CLIENT_REQUEST_TIMEOUT_SECS);
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err("Client went away".into()),
+            Err(_) => {
+                self.pending.lock().await.remove(&id);
+                Err(format!("Timeout: client did not answer {} within {}s", method, CLIENT_REQUEST_TIMEOUT_SECS))
+            }
+        }
+    }
+
+    /// Hand a response message from the client to the request waiting on it.
+    pub async fn resolve(&self, message: Value) {
+        let id = match message.get("id").and_then(|v| v.as_u64()) {
+            Some(id) => id,
+            None => return,
+        };
+        if let Some(tx) = self.pending.lock().await.remove(&id) {
+            let _ = tx.send(message);
+        }
+    }
+}
+
+/// The client request an upstream call is made on behalf of.
+#[derive(Debug, Clone)]
+pub struct Caller {
+    pub peer: Arc<ClientPeer>,
+    /// The client's JSON-RPC id, so its `notifications/cancelled` can be routed.
+    pub request_id: Option<Value>,
+}
+
+impl Caller {
+    /// Key of this request in per-session tables: (session, client request id).
+    pub fn key(&self) -> Option<(u64, String)> {
+        self.request_id.as_ref().map(|id| request_key(&self.peer, id))
+    }
+}
+
+/// (session, client request id); ids keep their JSON form, so `1` and `"1"` differ.
+pub fn request_key(peer: &ClientPeer, request_id: &Value) -> (u64, String) {
+    (peer.id(), request_id.to_string())
+}
+
+/// True for a JSON-RPC response (as opposed to a request or notification).
+pub fn is_response(message: &Value) -> bool {
+    message.get("method").is_none() && message.get("id").is_some()
+}
@@ -0,0 +1,137 @@
+//! Process groups of stdio children.
+//! Servers usually run through `npx`, `uvx` or `sh`, and killing only that
+//! wrapper orphaned the node/python process doing the work. Each child now
+//! leads its own process group and is stopped as a group. Groups are recorded
+//! in ~/.McpHub/process-groups until they are gone, so `McpHub doctor` can
+//! report any that outlived their server.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Serializes read-modify-write of the registry within this process.
+static REGISTRY_LOCK: Mutex<()> = Mutex::new(());
+
+/// One registry line: `<pgid> <daemon pid> <server>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    pub pgid: u32,
+    pub daemon_pid: u32,
+    pub server: String,
+}
+
+fn registry_path() -> PathBuf {
+    dirs::home_dir().unwrap_or_default().join(".McpHub").join("process-groups")
+}
+
+fn read() -> Vec<Group> {
+    let content = std::fs::read_to_string(registry_path()).unwrap_or_default();
+    content.lines().filter_map(parse_line).collect()
+}
+
+fn parse_line(line: &str) -> Option<Group> {
+    let mut parts = line.splitn(3, ' ');
+    Some(Group {
+        pgid: parts.next()?.parse().ok()?,
+        daemon_pid: parts.next()?.parse().ok()?,
+        server: parts.next()?.to_string(),
+    })
+}
+
+fn write(groups: &[Group]) {
+    let content: String = groups
+        .iter()
+        .map(|g| format!("{} {} {}\n", g.pgid, g.daemon_pid, g.server))
+        .collect();
+    let _ = std::fs::write(registry_path(), content);
+}
+
+/// Remember a freshly spawned group; entries for groups already gone are dropped.
+/// The registry file is rewritten on a blocking thread.
+pub async fn record(pgid: u32, server: &str) {
+    let server = server.to_string();
+    let _ = tokio::task::spawn_blocking(move || {
+        let _guard = REGISTRY_LOCK.lock();
+        let mut groups: Vec<Group> = read().into_iter().filter(|g| alive(g.pgid)).collect();
+        groups.push(Group { pgid, daemon_pid: std::process::id(), server });
+        write(&groups);
+    })
+    .await;
+}
+
+/// The group has been stopped.
+pub async fn forget(pgid: u32) {
+    let _ = tokio::task::spawn_blocking(move || {
+        let _guard = REGISTRY_LOCK.lock();
+        let groups: Vec<Group> = read().into_iter().filter(|g| g.pgid != pgid).collect();
+        write(&groups);
+    })
+    .await;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Signal {
+    Term,
+    Kill,
+}
+
+/// Send `signal` to every process in the group.
+#[cfg(unix)]
+pub fn signal(pgid: u32, signal: Signal) -> bool {
+    let signal = match signal {
+        Signal::Term => libc::SIGTERM,
+        Signal::Kill => libc::SIGKILL,
+    };
+    kill(-as_pid(pgid), signal)
+}
+
+#[cfg(not(unix))]
+pub fn signal(_pgid: u32, _signal: Signal) -> bool {
+    false
+}
+
+/// Some process of the group still exists (zombies included).
+#[cfg(unix)]
+pub fn alive(pgid: u32) -> bool {
+    kill(-as_pid(pgid), 0)
+}
+
+#[cfg(not(unix))]
+pub fn alive(_pgid: u32) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn pid_alive(pid: u32) -> bool {
+    kill(as_pid(pid), 0)
+}
+
+#[cfg(not(unix))]
+fn pid_alive(_pid: u32) -> bool {
+    false
+}
+
+/// `pid` as kill(2) takes it. 0 and out-of-range ids (from a damaged registry)
+/// become i32::MAX, which names no process, instead of our own group.
+#[cfg(unix)]
+fn as_pid(pid: u32) -> i32 {
+    i32::try_from(pid).ok().filter(|p| *p > 0).unwrap_or(i32::MAX)
+}
+
+/// kill(2). Probing with signal 0 counts a process we may not signal as alive.
+#[cfg(unix)]
+fn kill(pid: i32, signal: i32) -> bool {
+    // SAFETY: plain kill with integer arguments.
+    if unsafe { libc::kill(pid, signal) } == 0 {
+        return true;
+    }
+    signal == 0 && std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Recorded groups with processes left whose server is gone: the daemon that
+/// started them has exited, or the server process leading the group has.
+pub fn leftovers() -> Vec<Group> {
+    read()
+        .into_iter()
+        .filter(|g| alive(g.pgid) && (!pid_alive(g.daemon_pid) || !pid_alive(g.pgid)))
+        .collect()
+}
@@ -8,6 +8,7 @@ use serde_json::Value;
 
 #[derive(Debug, Deserialize)]
 pub struct JsonRpcRequest {
+    #[allow(dead_code)]
     pub jsonrpc: String,
     pub id: Option<Value>,
     pub method: String,
@@ -26,6 +27,9 @@ pub struct JsonRpcResponse {
     pub error: Option<JsonRpcError>,
 }
 
+/// Error code for a request the client cancelled (as in LSP).
+pub const REQUEST_CANCELLED: i64 = -32800;
+
 #[derive(Debug, Serialize)]
 pub struct JsonRpcError {
     pub code: i64,
@@ -53,13 +57,27 @@ impl JsonRpcResponse {
 
 // ─── MCP Tool Types ──────────────────────────────────────────
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A tool as listed by its server. Every field survives the round trip through
+/// the cache and passthrough `tools/list`, including ones we don't know yet.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ToolDef {
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
     #[serde(default)]
     pub description: String,
     #[serde(rename = "inputSchema", default)]
     pub input_schema: Value,
+    #[serde(rename = "outputSchema", default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+    /// Behaviour hints: readOnlyHint, destructiveHint, idempotentHint, openWorldHint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Value>,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+    /// Fields from newer protocol revisions, kept verbatim.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>,
 }
 
 #[derive(Debug, Serialize)]
@@ -77,6 +95,86 @@ impl ToolContent {
     }
 }
 
+// ─── Protocol Versions ───────────────────────────────────────
+
+/// Protocol revisions we speak, newest first. Revisions are dates, so they
+/// compare correctly as strings.
+pub const SUPPORTED_PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+pub const LATEST_PROTOCOL_VERSION: &str = SUPPORTED_PROTOCOL_VERSIONS[0];
+
+pub fn is_supported_version(version: &str) -> bool {
+    SUPPORTED_PROTOCOL_VERSIONS.contains(&version)
+}
+
+/// Answer to a client's `initialize`: the version it asked for if we speak it,
+/// otherwise our newest (the client then decides whether to continue).
+pub fn negotiate_version(requested: Option<&str>) -> &'static str {
+    SUPPORTED_PROTOCOL_VERSIONS
+        .iter()
+        .find(|v| Some(**v) == requested)
+        .copied()
+        .unwrap_or(LATEST_PROTOCOL_VERSION)
+}
+
+/// Reshape a `tools/call` result from a newer server for a client on `version`.
+/// - before 2025-06-18: `structuredContent` is dropped (kept as JSON text when it
+///   was the only output) and `resource_link` blocks become text
+/// - before 2025-03-26: audio blocks become text
+pub fn adapt_tool_result(result: &mut Value, version: &str) {
+    let obj = match result.as_object_mut() {
+        Some(obj) => obj,
+        None => return,
+    };
+
+    if version < "2025-06-18" {
+        if let Some(structured) = obj.remove("structuredContent") {
+            let empty = obj.get("content").and_then(|c| c.as_array()).is_none_or(|c| c.is_empty());
+            if empty {
+                obj.insert("content".into(), serde_json::json!([{ "type": "text", "text": structured.to_string() }]));
+            }
+        }
+    }
+
+    if let Some(content) = obj.get_mut("content").and_then(|c| c.as_array_mut()) {
+        for block in content.iter_mut() {
+            let text = match block.get("type").and_then(|t| t.as_str()) {
+                Some("resource_link") if version < "2025-06-18" => {
+                    let uri = block.get("uri").and_then(|v| v.as_str()).unwrap_or("");
+                    let name = block.get("name").and_then(|v| v.as_str()).unwrap_or(uri);
+                    format!("{} ({})", name, uri)
+                }
+                Some("audio") if version < "2025-03-26" => {
+                    let mime = block.get("mimeType").and_then(|v| v.as_str()).unwrap_or("audio");
+                    format!("[{} content omitted]", mime)
+                }
+                _ => continue,
+            };
+            *block = serde_json::json!({ "type": "text", "text": text });
+        }
+    }
+}
+
+/// Drop the tool fields a client on `version` does not know from a `tools/list` array.
+/// - before 2025-06-18: `title`, `outputSchema` and `_meta`
+/// - before 2025-03-26: `annotations`
+pub fn adapt_tool_list(tools: &mut Value, version: &str) {
+    let tools = match tools.as_array_mut() {
+        Some(tools) => tools,
+        None => return,
+    };
+
+    for tool in tools.iter_mut().filter_map(|t| t.as_object_mut()) {
+        if version < "2025-06-18" {
+            for field in ["title", "outputSchema", "_meta"] {
+                tool.remove(field);
+            }
+        }
+        if version < "2025-03-26" {
+            tool.remove("annotations");
+        }
+    }
+}
+
 // ─── MCP Initialize Types ────────────────────────────────────
 
 #[derive(Debug, Serialize)]
@@ -104,7 +202,11 @@ pub struct Capabilities {
 }
 
 #[derive(Debug, Serialize)]
-pub struct ToolsCapability {}
+pub struct ToolsCapability {
+    /// We send `notifications/tools/list_changed` (passthrough mode only).
+    #[serde(rename = "listChanged", skip_serializing_if = "std::ops::Not::not")]
+    pub list_changed: bool,
+}
 
 #[derive(Debug, Serialize)]
 pub struct PromptsCapability {}
@@ -147,12 +249,94 @@ mod tests {
         assert!(!resp_str.contains("result"));
     }
 
+    #[test]
+    fn test_tool_def_round_trip() {
+        let listed = json!({
+            "name": "drop_table",
+            "title": "Drop table",
+            "description": "Deletes a table",
+            "inputSchema": { "type": "object" },
+            "outputSchema": { "type": "object", "properties": { "ok": { "type": "boolean" } } },
+            "annotations": { "destructiveHint": true, "readOnlyHint": false },
+            "_meta": { "vendor/x": 1 },
+            "icons": [{ "src": "https://example.com/i.png" }]
+        });
+        let tool: ToolDef = serde_json::from_value(listed.clone()).unwrap();
+        assert_eq!(tool.annotations.as_ref().unwrap()["destructiveHint"], true);
+        assert!(tool.extra.contains_key("icons"));
+        assert_eq!(serde_json::to_value(&tool).unwrap(), listed);
+
+        let bare: ToolDef = serde_json::from_value(json!({ "name": "ping", "description": "", "inputSchema": {} })).unwrap();
+        assert_eq!(serde_json::to_string(&bare).unwrap(), r#"{"name":"ping","description":"","inputSchema":{}}"#);
+    }
+
+    #[test]
+    fn test_negotiate_version() {
+        assert_eq!(negotiate_version(Some("2025-03-26")), "2025-03-26");
+        assert_eq!(negotiate_version(Some("2024-11-05")), "2024-11-05");
+        assert_eq!(negotiate_version(Some("1999-01-01")), LATEST_PROTOCOL_VERSION);
+        assert_eq!(negotiate_version(None), LATEST_PROTOCOL_VERSION);
+    }
+
+    #[test]
+    fn test_adapt_tool_result() {
+        let newest = json!({
+            "content": [
+                { "type": "resource_link", "uri": "file:///a.txt", "name": "a.txt" },
+                { "type": "audio", "data": "AAA", "mimeType": "audio/wav" }
+            ],
+            "structuredContent": { "ok": true }
+        });
+
+        let mut same = newest.clone();
+        adapt_tool_result(&mut same, "2025-06-18");
+        assert_eq!(same, newest);
+
+        let mut mid = newest.clone();
+        adapt_tool_result(&mut mid, "2025-03-26");
+        assert!(mid.get("structuredContent").is_none());
+        assert_eq!(mid["content"][0], json!({ "type": "text", "text": "a.txt (file:///a.txt)" }));
+        assert_eq!(mid["content"][1]["type"], "audio");
+
+        let mut old = newest.clone();
+        adapt_tool_result(&mut old, "2024-11-05");
+        assert_eq!(old["content"][1]["type"], "text");
+
+        let mut structured_only = json!({ "content": [], "structuredContent": { "n": 1 } });
+        adapt_tool_result(&mut structured_only, "2025-03-26");
+        assert_eq!(structured_only["content"][0]["text"], r#"{"n":1}"#);
+    }
+
+    #[test]
+    fn test_adapt_tool_list() {
+        let newest = json!([{
+            "name": "get",
+            "title": "Get",
+            "inputSchema": { "type": "object" },
+            "outputSchema": { "type": "object" },
+            "annotations": { "readOnlyHint": true },
+            "_meta": { "k": 1 }
+        }]);
+
+        let mut same = newest.clone();
+        adapt_tool_list(&mut same, "2025-06-18");
+        assert_eq!(same, newest);
+
+        let mut mid = newest.clone();
+        adapt_tool_list(&mut mid, "2025-03-26");
+        assert_eq!(mid, json!([{ "name": "get", "inputSchema": { "type": "object" }, "annotations": { "readOnlyHint": true } }]));
+
+        let mut old = newest.clone();
+        adapt_tool_list(&mut old, "2024-11-05");
+        assert_eq!(old, json!([{ "name": "get", "inputSchema": { "type": "object" } }]));
+    }
+
     #[test]
     fn test_capabilities_serialization() {
         let init_result = InitializeResult {
             protocol_version: "2024-11-05".to_string(),
             capabilities: Capabilities {
-                tools: ToolsCapability {},
+                tools: ToolsCapability { list_changed: false },
                 prompts: PromptsCapability {},
                 resources: ResourcesCapability {},
             },
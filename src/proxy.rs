@@ -1,37 +1,53 @@
 /// Core proxy server: reads JSON-RPC from stdin, routes to child servers.
 /// Two modes: discover (2 meta-tools) or passthrough (all tools exposed).
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 use std::time::{Instant, SystemTime};
 
-use tokio::io::{self, AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{self, AsyncBufReadExt, BufReader};
 use tokio::sync::Mutex;
 
 use crate::child::ChildManager;
-use crate::config::{Mode, Preload, ProxyConfig};
+use crate::config::{LargeResults, Mode, Preload, ProxyConfig};
 use crate::health::HealthMonitor;
+use crate::offload::ResultStore;
+use crate::peer::{Caller, ClientPeer};
 use crate::protocol::*;
 use crate::search::{IndexedTool, SearchEngine};
 
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, Default, serde::Serialize)]
 pub struct ServerMetrics {
     pub call_count: u64,
     pub error_count: u64,
     pub total_latency_ms: u64,
     pub last_call_time: Option<SystemTime>,
     pub last_error: Option<String>,
-}
-
-impl Default for ServerMetrics {
-    fn default() -> Self {
-        Self {
-            call_count: 0,
-            error_count: 0,
-            total_latency_ms: 0,
-            last_call_time: None,
-            last_error: None,
-        }
-    }
+    /// Running instances, refreshed when metrics are read.
+    pub instances: usize,
+    /// Calls outstanding across those instances.
+    pub queue_depth: usize,
+    /// Circuit breaker state: closed, open or half_open.
+    pub circuit: String,
+    /// Seconds until an open circuit lets a trial call through.
+    pub retry_after_secs: u64,
+    /// Times the circuit opened.
+    pub circuit_trips: u64,
+    /// Calls failed fast while the circuit was open.
+    pub circuit_rejected: u64,
+    /// Calls queued behind `maxConcurrent` or `rateLimit` right now.
+    pub throttle_waiting: usize,
+    /// Calls that had to queue.
+    pub throttled: u64,
+    /// Calls that gave up after `queueTimeout`.
+    pub throttle_rejected: u64,
+    /// Calls answered from the result cache.
+    pub cache_hits: u64,
+    /// Cacheable calls that had to reach the server.
+    pub cache_misses: u64,
+    /// Results cached right now.
+    pub cache_entries: usize,
+    /// Calls that shared an identical in-flight call's request.
+    pub coalesced_calls: u64,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -39,6 +55,7 @@ pub struct GlobalMetrics {
     pub start_time: SystemTime,
     pub total_requests: u64,
     pub active_sse_sessions: usize,
+    pub active_http_sessions: usize,
     pub servers: HashMap<String, ServerMetrics>,
 }
 
@@ -48,6 +65,7 @@ impl GlobalMetrics {
             start_time: SystemTime::now(),
             total_requests: 0,
             active_sse_sessions: 0,
+            active_http_sessions: 0,
             servers: HashMap::new(),
         }
     }
@@ -58,6 +76,10 @@ pub struct ProxyServer {
     child_manager: Arc<ChildManager>,
     search_engine: Arc<Mutex<SearchEngine>>,
     pub metrics: Arc<Mutex<GlobalMetrics>>,
+    /// Every client that initialized (stdio, SSE and Streamable HTTP sessions).
+    clients: std::sync::Mutex<Vec<Weak<ClientPeer>>>,
+    /// Full text of results too large to return whole, for `fetch_result`.
+    offloaded: ResultStore,
 }
 
 impl ProxyServer {
@@ -72,12 +94,14 @@ impl ProxyServer {
             child_manager,
             search_engine: Arc::new(Mutex::new(SearchEngine::new())),
             metrics: Arc::new(Mutex::new(GlobalMetrics::new())),
+            clients: std::sync::Mutex::new(Vec::new()),
+            offloaded: ResultStore::default(),
         }
     }
 
     /// Initialize proxy: load cache, start background tasks.
     /// Call this before stdio_loop() or serving SSE.
-    pub async fn init(&self) {
+    pub async fn init(self: &Arc<Self>) {
         // 1. Load cache synchronously FIRST (instant, <1ms)
         if let Some(cached) = crate::cache::load_cache() {
             let mut all_tools: Vec<IndexedTool> = Vec::new();
@@ -118,7 +142,17 @@ impl ProxyServer {
             config_and_cache_watcher(engine_watch, config_watch, child_manager_watch).await;
         });
 
-        // 4. Start health monitor (notifications + auto-restart)
+        // 4. Follow tools/list_changed from children
+        if let Some(mut changed) = self.child_manager.take_tools_changed() {
+            let proxy = self.clone();
+            tokio::spawn(async move {
+                while let Some(server) = changed.recv().await {
+                    proxy.refresh_server_tools(&server).await;
+                }
+            });
+        }
+
+        // 5. Start health monitor (notifications + auto-restart)
         let config = self.config.lock().await;
         if config.health_notifications {
             let monitor = HealthMonitor::new(
@@ -133,7 +167,7 @@ impl ProxyServer {
     }
 
     /// Full run: init + stdio loop. Backward compatible.
-    pub async fn run(&self) {
+    pub async fn run(self: &Arc<Self>) {
         self.init().await;
         self.stdio_loop().await;
     }
@@ -142,6 +176,108 @@ impl ProxyServer {
         self.child_manager.stop_all().await;
     }
 
+    /// A child reported `notifications/tools/list_changed`: re-list its tools,
+    /// update the cache and that server's index entries, and tell passthrough clients.
+    async fn refresh_server_tools(&self, server: &str) {
+        let tools = match self.child_manager.refresh_tools(server).await {
+            Ok(tools) => tools,
+            Err(e) => {
+                eprintln!("[McpHub][WARN] Could not re-list tools of '{}': {}", server, e);
+                return;
+            }
+        };
+        // The schema cache is a file read and rewritten in full
+        let (name, listed) = (server.to_string(), tools.clone());
+        let _ = tokio::task::spawn_blocking(move || crate::cache::repair_server_cache(&name, listed)).await;
+
+        let indexed: Vec<IndexedTool> = tools
+            .into_iter()
+            .map(|tool| IndexedTool {
+                name: format!("{}__{}", server, tool.name),
+                original_name: tool.name.clone(),
+                server_name: server.to_string(),
+                description: tool.description.clone(),
+                tool_def: tool,
+            })
+            .collect();
+        eprintln!("[McpHub][INFO] Tools of '{}' changed: {} tools", server, indexed.len());
+        self.search_engine.lock().await.replace_server(server, indexed);
+
+        // Discover mode only exposes discover/execute, which never change
+        if self.config.lock().await.mode == Mode::Passthrough {
+            self.notify_clients("notifications/tools/list_changed").await;
+        }
+    }
+
+    /// Send a parameterless notification to every connected client.
+    async fn notify_clients(&self, method: &str) {
+        let clients: Vec<Arc<ClientPeer>> = match self.clients.lock() {
+            Ok(mut clients) => {
+                clients.retain(|c| c.strong_count() > 0);
+                clients.iter().filter_map(|c| c.upgrade()).collect()
+            }
+            Err(_) => return,
+        };
+        let notification = serde_json::json!({ "jsonrpc": "2.0", "method": method });
+        for client in clients {
+            // Streamable HTTP clients without a GET stream can't be reached; skip them
+            let _ = client.send(&notification).await;
+        }
+    }
+
+    /// Fill in live pool, circuit, throttle, cache and coalescing figures before metrics are served.
+    pub async fn refresh_pool_metrics(&self) {
+        let stats = self.child_manager.pool_stats().await;
+        let circuits = self.child_manager.breaker_statuses();
+        let throttles = self.child_manager.throttle_stats();
+        let caches = self.child_manager.cache_stats();
+        let coalesced = self.child_manager.coalesced_stats();
+        let mut m = self.metrics.lock().await;
+        for sm in m.servers.values_mut() {
+            sm.instances = 0;
+            sm.queue_depth = 0;
+            sm.circuit = "closed".into();
+            sm.retry_after_secs = 0;
+        }
+        for (name, instances, queue_depth) in stats {
+            let sm = m.servers.entry(name).or_default();
+            sm.instances = instances;
+            sm.queue_depth = queue_depth;
+        }
+        for (name, status) in circuits {
+            let sm = m.servers.entry(name).or_default();
+            sm.circuit = status.state.as_str().into();
+            sm.retry_after_secs = status.retry_after_secs;
+            sm.circuit_trips = status.trips;
+            sm.circuit_rejected = status.rejected;
+        }
+        for (name, throttle) in throttles {
+            let sm = m.servers.entry(name).or_default();
+            sm.throttle_waiting = throttle.waiting;
+            sm.throttled = throttle.throttled;
+            sm.throttle_rejected = throttle.rejected;
+        }
+        for (name, cache) in caches {
+            let sm = m.servers.entry(name).or_default();
+            sm.cache_hits = cache.hits;
+            sm.cache_misses = cache.misses;
+            sm.cache_entries = cache.entries;
+        }
+        for (name, calls) in coalesced {
+            m.servers.entry(name).or_default().coalesced_calls = calls;
+        }
+    }
+
+    /// Drop the cached tool results of a server. Returns how many there were.
+    pub fn invalidate_cache(&self, server_name: &str) -> usize {
+        self.child_manager.invalidate_cache(server_name)
+    }
+
+    /// Recent stderr output of a child server, for the dashboard.
+    pub fn stderr_lines(&self, server_name: &str) -> Vec<String> {
+        self.child_manager.stderr_lines(server_name)
+    }
+
     async fn servers_to_preload(&self) -> Vec<String> {
         let config = self.config.lock().await;
         match &config.preload {
@@ -151,9 +287,11 @@ impl ProxyServer {
         }
     }
 
-    pub async fn stdio_loop(&self) {
+    /// Requests are handled concurrently so the client's answers to relayed
+    /// server requests can be read while the call that triggered them is pending.
+    pub async fn stdio_loop(self: &Arc<Self>) {
         let stdin = io::stdin();
-        let mut stdout = io::stdout();
+        let peer = Arc::new(ClientPeer::stdio());
         let reader = BufReader::new(stdin);
         let mut lines = reader.lines();
 
@@ -163,40 +301,56 @@ impl ProxyServer {
                 continue;
             }
 
-            let request: JsonRpcRequest = match serde_json::from_str(&line) {
+            let message: serde_json::Value = match serde_json::from_str(&line) {
+                Ok(m) => m,
+                Err(_) => continue,
+            };
+            if crate::peer::is_response(&message) {
+                peer.resolve(message).await;
+                continue;
+            }
+            let request: JsonRpcRequest = match serde_json::from_value(message) {
                 Ok(r) => r,
                 Err(_) => continue,
             };
 
-            let response = self.handle_request(request).await;
-
-            if let Some(resp) = response {
-                let mut msg = serde_json::to_string(&resp).unwrap();
-                msg.push('\n');
-                let _ = stdout.write_all(msg.as_bytes()).await;
-                let _ = stdout.flush().await;
-            }
+            let proxy = self.clone();
+            let peer = peer.clone();
+            tokio::spawn(async move {
+                if let Some(resp) = proxy.handle_request(request, Some(&peer)).await {
+                    let _ = peer.send(&serde_json::to_value(&resp).unwrap()).await;
+                }
+            });
         }
 
         // Cleanup
         self.child_manager.stop_all().await;
     }
 
-    pub async fn handle_request(&self, req: JsonRpcRequest) -> Option<JsonRpcResponse> {
+    /// `peer` is the client that sent `req`; upstream requests made while
+    /// serving it are relayed there.
+    pub async fn handle_request(&self, req: JsonRpcRequest, peer: Option<&Arc<ClientPeer>>) -> Option<JsonRpcResponse> {
+        let caller = peer.map(|peer| Caller { peer: peer.clone(), request_id: req.id.clone() });
+        let caller = caller.as_ref();
         match req.method.as_str() {
-            "initialize" => Some(self.handle_initialize(req.id).await),
+            "initialize" => {
+                if let (Some(peer), Ok(mut clients)) = (peer, self.clients.lock()) {
+                    clients.push(Arc::downgrade(peer));
+                }
+                Some(self.handle_initialize(req.id, req.params, peer).await)
+            }
             "notifications/initialized" => None,
-            "tools/list" => Some(self.handle_tools_list(req.id).await),
-            "tools/call" => Some(self.handle_tools_call(req.id, req.params).await),
+            "tools/list" => Some(self.handle_tools_list(req.id, peer).await),
+            "tools/call" => Some(self.handle_tools_call(req.id, req.params, caller).await),
             "prompts/list" => Some(self.handle_prompts_list(req.id).await),
-            "prompts/get" => Some(self.handle_prompts_get(req.id, req.params).await),
+            "prompts/get" => Some(self.handle_prompts_get(req.id, req.params, caller).await),
             "resources/list" => Some(self.handle_resources_list(req.id).await),
             "resources/templates/list" => Some(self.handle_resource_templates_list(req.id).await),
-            "resources/read" => Some(self.handle_resources_read(req.id, req.params).await),
+            "resources/read" => Some(self.handle_resources_read(req.id, req.params, caller).await),
             "completion/complete" => Some(JsonRpcResponse::success(req.id, serde_json::json!({ "completion": { "values": [] } }))),
             "ping" => Some(JsonRpcResponse::success(req.id, serde_json::json!({}))),
             "notifications/cancelled" => {
-                self.handle_cancel(req.params).await;
+                self.handle_cancel(req.params, peer).await;
                 None
             }
             _ => {
@@ -210,7 +364,17 @@ impl ProxyServer {
         }
     }
 
-    async fn handle_initialize(&self, id: Option<serde_json::Value>) -> JsonRpcResponse {
+    async fn handle_initialize(
+        &self,
+        id: Option<serde_json::Value>,
+        params: serde_json::Value,
+        peer: Option<&Arc<ClientPeer>>,
+    ) -> JsonRpcResponse {
+        let version = negotiate_version(params.get("protocolVersion").and_then(|v| v.as_str()));
+        if let Some(peer) = peer {
+            peer.set_protocol_version(version);
+        }
+
         let config = self.config.lock().await;
         let mode_str = match config.mode {
             Mode::Discover => "discover",
@@ -218,15 +382,16 @@ impl ProxyServer {
         };
 
         eprintln!(
-            "[McpHub][INFO] Initialize: mode={}, servers={}",
+            "[McpHub][INFO] Initialize: mode={}, servers={}, protocol={}",
             mode_str,
-            config.servers.len()
+            config.servers.len(),
+            version
         );
 
         let result = InitializeResult {
-            protocol_version: "2024-11-05".into(),
+            protocol_version: version.into(),
             capabilities: Capabilities {
-                tools: ToolsCapability {},
+                tools: ToolsCapability { list_changed: config.mode == Mode::Passthrough },
                 prompts: PromptsCapability {},
                 resources: ResourcesCapability {},
             },
@@ -247,16 +412,26 @@ impl ProxyServer {
     async fn handle_tools_list(
         &self,
         id: Option<serde_json::Value>,
+        peer: Option<&Arc<ClientPeer>>,
     ) -> JsonRpcResponse {
         let mode = {
             let config = self.config.lock().await;
             config.mode.clone()
         };
 
-        let tools = match mode {
+        let mut tools = match mode {
             Mode::Discover => self.get_discover_tools().await,
             Mode::Passthrough => self.get_passthrough_tools().await,
         };
+        let large_results = self.config.lock().await.large_results.clone();
+        if large_results.enabled {
+            if let Some(tools) = tools.as_array_mut() {
+                tools.push(fetch_result_tool(&large_results));
+            }
+        }
+        if let Some(peer) = peer {
+            adapt_tool_list(&mut tools, peer.protocol_version());
+        }
 
         JsonRpcResponse::success(id, serde_json::json!({ "tools": tools }))
     }
@@ -357,6 +532,7 @@ impl ProxyServer {
         &self,
         id: Option<serde_json::Value>,
         params: serde_json::Value,
+        caller: Option<&Caller>,
     ) -> JsonRpcResponse {
         let tool_name = params
             .get("name")
@@ -366,23 +542,29 @@ impl ProxyServer {
             .get("arguments")
             .cloned()
             .unwrap_or(serde_json::json!({}));
+        let progress_token = params.pointer("/_meta/progressToken").cloned();
 
-        let mode = {
+        let (mode, offloading) = {
             let config = self.config.lock().await;
-            config.mode.clone()
+            (config.mode.clone(), config.large_results.enabled)
         };
 
+        // Only while advertised; otherwise an upstream tool of that name is meant
+        if tool_name == "fetch_result" && offloading {
+            return self.handle_fetch_result(id, arguments).await;
+        }
+
         match mode {
             Mode::Discover => match tool_name {
                 "discover" => self.handle_discover(id, arguments).await,
-                "execute" => self.handle_execute(id, arguments).await,
+                "execute" => self.handle_execute(id, arguments, caller, progress_token).await,
                 _ => JsonRpcResponse::error(
                     id,
                     -32602,
                     format!("Unknown tool: {}. Use 'discover' first.", tool_name),
                 ),
             },
-            Mode::Passthrough => self.handle_passthrough_call(id, tool_name, arguments).await,
+            Mode::Passthrough => self.handle_passthrough_call(id, tool_name, arguments, caller, progress_token).await,
         }
     }
 
@@ -414,12 +596,17 @@ impl ProxyServer {
                 }
                 let desc: String = t.description.chars().take(200).collect();
                 let schema = strip_schema(&t.tool_def.input_schema);
-                serde_json::json!({
+                let mut entry = serde_json::json!({
                     "server": t.server_name,
                     "tool": t.original_name,
                     "description": desc,
                     "inputSchema": schema,
-                })
+                });
+                // Lets agents spot destructive tools before calling execute
+                if let Some(annotations) = &t.tool_def.annotations {
+                    entry["annotations"] = annotations.clone();
+                }
+                entry
             }).collect();
 
             let text = serde_json::to_string(&serde_json::json!({
@@ -487,6 +674,8 @@ impl ProxyServer {
         &self,
         id: Option<serde_json::Value>,
         args: serde_json::Value,
+        caller: Option<&Caller>,
+        progress_token: Option<serde_json::Value>,
     ) -> JsonRpcResponse {
         let server = match args.get("server").and_then(|v| v.as_str()) {
             Some(s) => s.to_string(),
@@ -508,7 +697,7 @@ impl ProxyServer {
             .unwrap_or(serde_json::json!({}));
 
         let start_time = Instant::now();
-        let res = self.child_manager.call_tool(&server, &tool, arguments).await;
+        let res = self.child_manager.call_tool(&server, &tool, arguments, caller, progress_token).await;
         let elapsed = start_time.elapsed().as_millis() as u64;
 
         {
@@ -525,8 +714,14 @@ impl ProxyServer {
         }
 
         match res {
-            Ok(result) => JsonRpcResponse::success(id, result),
-            Err(e) => JsonRpcResponse::error(id, -32000, e),
+            Ok(mut result) => {
+                self.offload_large_result(&server, &tool, &mut result).await;
+                if let Some(caller) = caller {
+                    adapt_tool_result(&mut result, caller.peer.protocol_version());
+                }
+                JsonRpcResponse::success(id, result)
+            }
+            Err(e) => call_error(id, e),
         }
     }
 
@@ -535,6 +730,8 @@ impl ProxyServer {
         id: Option<serde_json::Value>,
         prefixed_name: &str,
         arguments: serde_json::Value,
+        caller: Option<&Caller>,
+        progress_token: Option<serde_json::Value>,
     ) -> JsonRpcResponse {
         // Parse "server__tool" format
         let parts: Vec<&str> = prefixed_name.splitn(2, "__").collect();
@@ -550,7 +747,7 @@ impl ProxyServer {
         let tool = parts[1];
 
         let start_time = Instant::now();
-        let res = self.child_manager.call_tool(server, tool, arguments).await;
+        let res = self.child_manager.call_tool(server, tool, arguments, caller, progress_token).await;
         let elapsed = start_time.elapsed().as_millis() as u64;
 
         {
@@ -567,8 +764,36 @@ impl ProxyServer {
         }
 
         match res {
+            Ok(mut result) => {
+                self.offload_large_result(server, tool, &mut result).await;
+                if let Some(caller) = caller {
+                    adapt_tool_result(&mut result, caller.peer.protocol_version());
+                }
+                JsonRpcResponse::success(id, result)
+            }
+            Err(e) => call_error(id, e),
+        }
+    }
+
+    /// Swap a result over the `largeResults` budget for a preview and a handle.
+    async fn offload_large_result(&self, server: &str, tool: &str, result: &mut serde_json::Value) {
+        let large_results = self.config.lock().await.large_results.clone();
+        if let Some(handle) = self.offloaded.offload(result, &large_results) {
+            eprintln!("[McpHub][INFO] Result of {}/{} over {} characters, stored as {}", server, tool, large_results.max_chars, handle);
+        }
+    }
+
+    async fn handle_fetch_result(&self, id: Option<serde_json::Value>, args: serde_json::Value) -> JsonRpcResponse {
+        let handle = match args.get("handle").and_then(|v| v.as_str()) {
+            Some(h) => h,
+            None => return JsonRpcResponse::error(id, -32602, "Missing 'handle' parameter".into()),
+        };
+        let offset = args.get("offset").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+        let length = args.get("length").and_then(|v| v.as_u64()).map(|n| n as usize);
+        let large_results = self.config.lock().await.large_results.clone();
+        match self.offloaded.fetch(handle, offset, length, &large_results) {
             Ok(result) => JsonRpcResponse::success(id, result),
-            Err(e) => JsonRpcResponse::error(id, -32000, e),
+            Err(e) => JsonRpcResponse::error(id, -32602, e),
         }
     }
 
@@ -590,7 +815,7 @@ impl ProxyServer {
         JsonRpcResponse::success(id, serde_json::json!({ "prompts": all_prompts }))
     }
 
-    async fn handle_prompts_get(&self, id: Option<serde_json::Value>, args: serde_json::Value) -> JsonRpcResponse {
+    async fn handle_prompts_get(&self, id: Option<serde_json::Value>, args: serde_json::Value, caller: Option<&Caller>) -> JsonRpcResponse {
         let name = args.get("name").and_then(|v| v.as_str()).unwrap_or("");
         let parts: Vec<&str> = name.splitn(2, "__").collect();
         if parts.len() != 2 {
@@ -602,9 +827,9 @@ impl ProxyServer {
         let mut new_args = args.clone();
         new_args["name"] = serde_json::json!(prompt_name);
         
-        match self.child_manager.call_method(server, "prompts/get", new_args).await {
+        match self.child_manager.call_method(server, "prompts/get", new_args, caller).await {
             Ok(res) => JsonRpcResponse::success(id, res),
-            Err(e) => JsonRpcResponse::error(id, -32000, e),
+            Err(e) => call_error(id, e),
         }
     }
 
@@ -644,7 +869,7 @@ impl ProxyServer {
         JsonRpcResponse::success(id, serde_json::json!({ "resourceTemplates": all_templates }))
     }
 
-    async fn handle_resources_read(&self, id: Option<serde_json::Value>, args: serde_json::Value) -> JsonRpcResponse {
+    async fn handle_resources_read(&self, id: Option<serde_json::Value>, args: serde_json::Value, caller: Option<&Caller>) -> JsonRpcResponse {
         let uri = args.get("uri").and_then(|v| v.as_str()).unwrap_or("");
         let parts: Vec<&str> = uri.splitn(2, "__").collect();
         if parts.len() != 2 {
@@ -656,23 +881,52 @@ impl ProxyServer {
         let mut new_args = args.clone();
         new_args["uri"] = serde_json::json!(actual_uri);
         
-        match self.child_manager.call_method(server, "resources/read", new_args).await {
+        match self.child_manager.call_method(server, "resources/read", new_args, caller).await {
             Ok(res) => JsonRpcResponse::success(id, res),
-            Err(e) => JsonRpcResponse::error(id, -32000, e),
+            Err(e) => call_error(id, e),
         }
     }
 
-    async fn handle_cancel(&self, args: serde_json::Value) {
-        // Just broadcast the cancellation to all running servers.
-        // ChildManager does not keep track of request IDs globally.
-        // The server will simply ignore the cancellation if it doesn't know the request ID.
-        let running_servers = self.child_manager.server_names().await;
-        for server in running_servers {
-            let _ = self.child_manager.forward_notification(&server, "notifications/cancelled", args.clone()).await;
-        }
+    /// Route a client's cancellation to the one child serving that request.
+    /// Cancels for requests that already finished are ignored.
+    async fn handle_cancel(&self, args: serde_json::Value, peer: Option<&Arc<ClientPeer>>) {
+        let (peer, request_id) = match (peer, args.get("requestId")) {
+            (Some(peer), Some(id)) => (peer, id),
+            _ => return,
+        };
+        let reason = args.get("reason").and_then(|v| v.as_str());
+        self.child_manager.cancel_request(peer, request_id, reason).await;
     }
 }
 
+/// Error response for a failed upstream call.
+fn call_error(id: Option<serde_json::Value>, e: String) -> JsonRpcResponse {
+    let code = if crate::child::is_cancelled(&e) { REQUEST_CANCELLED } else { -32000 };
+    JsonRpcResponse::error(id, code, e)
+}
+
+/// Meta-tool that pages through a result cut down by `largeResults`.
+fn fetch_result_tool(large_results: &LargeResults) -> serde_json::Value {
+    serde_json::json!({
+        "name": "fetch_result",
+        "description": format!(
+            "Read more of a tool result that was too large to return whole. Results over {} characters come back \
+            as a preview ending in a note with a handle; call this with that handle and an offset to read on.",
+            large_results.max_chars
+        ),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "handle": { "type": "string", "description": "Handle from the truncated result (res_...)" },
+                "offset": { "type": "number", "description": "Character to start at (default: 0)", "default": 0 },
+                "length": { "type": "number", "description": format!("Characters to return (default: 10000, max: {})", large_results.max_chars) }
+            },
+            "required": ["handle"]
+        },
+        "annotations": { "readOnlyHint": true }
+    })
+}
+
 /// Strip noise from inputSchema: remove title, examples, $schema, additionalProperties.
 /// Keeps type, properties, required, description (on root only), items, enum.
 fn strip_schema(schema: &serde_json::Value) -> serde_json::Value {
@@ -0,0 +1,364 @@
+//! Remote MCP upstreams: servers reached over HTTP instead of a spawned process.
+//! - Streamable HTTP (2025-03-26): every JSON-RPC message is a POST to one endpoint,
+//!   answered with plain JSON or an SSE stream carrying the response.
+//! - HTTP+SSE (2024-11-05): a long-lived `GET /sse` stream announces a POST endpoint;
+//!   responses to those POSTs arrive as events on the stream.
+
+use std::collections::HashMap;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE};
+use serde_json::Value;
+
+use crate::child::Inbox;
+
+const SESSION_HEADER: &str = "mcp-session-id";
+const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+
+/// Client for one Streamable HTTP upstream connection.
+/// Holds the `Mcp-Session-Id` handed out by the server on initialize, and the
+/// negotiated version sent as `MCP-Protocol-Version` on every later request.
+#[derive(Debug)]
+pub struct StreamableHttpClient {
+    http: reqwest::Client,
+    url: String,
+    session_id: std::sync::Mutex<Option<String>>,
+    protocol_version: std::sync::Mutex<Option<&'static str>>,
+}
+
+impl StreamableHttpClient {
+    pub fn new(url: &str, headers: &HashMap<String, String>) -> Result<Self, String> {
+        let http = reqwest::Client::builder()
+            .default_headers(build_headers(headers)?)
+            .build()
+            .map_err(|e| format!("HTTP client error: {}", e))?;
+        Ok(Self {
+            http,
+            url: url.to_string(),
+            session_id: std::sync::Mutex::new(None),
+            protocol_version: std::sync::Mutex::new(None),
+        })
+    }
+
+    /// POST one JSON-RPC message. Whatever comes back — a JSON body or an SSE
+    /// stream — is handed to `inbox`; SSE streams are drained in the background
+    /// so concurrent requests never wait on each other.
+    pub async fn send(&self, message: &Value, inbox: &Inbox) -> Result<(), String> {
+        let resp = self.post(message).await?;
+        let request_id = if message.get("method").is_some() {
+            message.get("id").and_then(|v| v.as_u64())
+        } else {
+            None
+        };
+
+        let content_type = resp
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_lowercase();
+
+        if content_type.starts_with("text/event-stream") {
+            tokio::spawn(pump_response_stream(resp, inbox.clone(), request_id));
+            return Ok(());
+        }
+
+        let body = resp.text().await.map_err(|e| format!("Read error: {}", e))?;
+        dispatch_payload(&body, inbox, request_id).await;
+        if let Some(id) = request_id {
+            // A request answered without its response: fail the caller now
+            inbox.forget(id).await;
+        }
+        Ok(())
+    }
+
+    /// End the upstream session (DELETE with the session header), if one was issued.
+    pub async fn close(&self) {
+        let session_id = self.session_id.lock().ok().and_then(|mut s| s.take());
+        if let Some(session_id) = session_id {
+            let _ = self
+                .http
+                .delete(&self.url)
+                .header(SESSION_HEADER, session_id)
+                .send()
+                .await;
+        }
+    }
+
+    pub fn set_protocol_version(&self, version: &'static str) {
+        if let Ok(mut v) = self.protocol_version.lock() {
+            *v = Some(version);
+        }
+    }
+
+    fn current_session(&self) -> Option<String> {
+        self.session_id.lock().ok().and_then(|s| s.clone())
+    }
+
+    fn set_session(&self, value: Option<String>) {
+        if let Ok(mut s) = self.session_id.lock() {
+            *s = value;
+        }
+    }
+
+    async fn post(&self, message: &Value) -> Result<reqwest::Response, String> {
+        let mut req = self
+            .http
+            .post(&self.url)
+            .header(ACCEPT, "application/json, text/event-stream")
+            .header(CONTENT_TYPE, "application/json")
+            .body(message.to_string());
+        let had_session = self.current_session();
+        if let Some(session_id) = &had_session {
+            req = req.header(SESSION_HEADER, session_id);
+        }
+        if let Some(version) = self.protocol_version.lock().ok().and_then(|v| *v) {
+            req = req.header(PROTOCOL_VERSION_HEADER, version);
+        }
+
+        let resp = req.send().await.map_err(|e| format!("Write error: {}", e))?;
+
+        if let Some(sid) = resp.headers().get(SESSION_HEADER).and_then(|v| v.to_str().ok()) {
+            self.set_session(Some(sid.to_string()));
+        }
+
+        let status = resp.status();
+        if status.as_u16() == 404 && had_session.is_some() {
+            // Spec: 404 means the session expired; the caller must re-initialize.
+            self.set_session(None);
+            return Err("Server closed connection: session expired".into());
+        }
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            let body: String = body.chars().take(200).collect();
+            return Err(format!("HTTP {}: {}", status.as_u16(), body.trim()));
+        }
+        Ok(resp)
+    }
+}
+
+/// Drain the SSE stream answering one POST. If it ends without the response
+/// to `request_id`, the waiting caller is failed instead of left to time out.
+async fn pump_response_stream(mut resp: reqwest::Response, inbox: Inbox, request_id: Option<u64>) {
+    let mut decoder = SseDecoder::default();
+    while let Ok(Some(chunk)) = resp.chunk().await {
+        for event in decoder.feed(&chunk) {
+            if event.event == "message" {
+                dispatch_payload(&event.data, &inbox, request_id).await;
+            }
+        }
+    }
+    if let Some(id) = request_id {
+        inbox.forget(id).await;
+    }
+}
+
+/// Parse one body or SSE data payload (a message or a batch) and dispatch it.
+/// `origin` is the request this payload answers, if any.
+async fn dispatch_payload(data: &str, inbox: &Inbox, origin: Option<u64>) {
+    let parsed: Value = match serde_json::from_str(data.trim()) {
+        Ok(v) => v,
+        Err(_) => return,
+    };
+    let messages = match parsed {
+        Value::Array(items) => items,
+        other => vec![other],
+    };
+    for msg in messages {
+        inbox.dispatch(msg, origin).await;
+    }
+}
+
+/// Max time to wait for the `endpoint` event after opening the SSE stream.
+const ENDPOINT_TIMEOUT_SECS: u64 = 30;
+
+/// Client for one legacy HTTP+SSE upstream connection.
+/// A reader task owns the event stream and hands every message to the inbox.
+#[derive(Debug)]
+pub struct LegacySseClient {
+    http: reqwest::Client,
+    endpoint: String,
+    reader: tokio::task::JoinHandle<()>,
+}
+
+impl LegacySseClient {
+    /// Open `GET url`, wait for the `endpoint` event, then keep reading in the background.
+    pub async fn connect(url: &str, headers: &HashMap<String, String>, inbox: Inbox) -> Result<Self, String> {
+        let http = reqwest::Client::builder()
+            .default_headers(build_headers(headers)?)
+            .build()
+            .map_err(|e| format!("HTTP client error: {}", e))?;
+
+        let mut resp = http
+            .get(url)
+            .header(ACCEPT, "text/event-stream")
+            .send()
+            .await
+            .map_err(|e| format!("Write error: {}", e))?;
+        if !resp.status().is_success() {
+            return Err(format!("HTTP {} opening SSE stream", resp.status().as_u16()));
+        }
+
+        let mut decoder = SseDecoder::default();
+        // Events that share a chunk with the endpoint are handed to the reader
+        let mut early = Vec::new();
+        let wait_endpoint = async {
+            loop {
+                let chunk = resp
+                    .chunk()
+                    .await
+                    .map_err(|e| format!("Read error: {}", e))?
+                    .ok_or("Server closed connection before sending endpoint")?;
+                let mut endpoint = None;
+                for event in decoder.feed(&chunk) {
+                    match event.event.as_str() {
+                        "endpoint" if endpoint.is_none() => endpoint = Some(event.data),
+                        _ => early.push(event),
+                    }
+                }
+                if let Some(endpoint) = endpoint {
+                    return Ok::<String, String>(endpoint);
+                }
+            }
+        };
+        let endpoint = tokio::time::timeout(std::time::Duration::from_secs(ENDPOINT_TIMEOUT_SECS), wait_endpoint)
+            .await
+            .map_err(|_| format!("Timeout: no endpoint event within {}s", ENDPOINT_TIMEOUT_SECS))??;
+        let endpoint = reqwest::Url::parse(url)
+            .and_then(|base| base.join(endpoint.trim()))
+            .map_err(|e| format!("Invalid endpoint '{}': {}", endpoint, e))?
+            .to_string();
+
+        let reader = tokio::spawn(read_events(resp, decoder, early, inbox));
+
+        Ok(Self { http, endpoint, reader })
+    }
+
+    /// POST a message to the endpoint. Responses arrive on the event stream.
+    pub async fn send(&self, message: &Value) -> Result<(), String> {
+        let resp = self
+            .http
+            .post(&self.endpoint)
+            .header(CONTENT_TYPE, "application/json")
+            .body(message.to_string())
+            .send()
+            .await
+            .map_err(|e| format!("Write error: {}", e))?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            let body: String = body.chars().take(200).collect();
+            return Err(format!("HTTP {}: {}", status.as_u16(), body.trim()));
+        }
+        Ok(())
+    }
+
+    pub fn close(&self) {
+        self.reader.abort();
+    }
+}
+
+/// Reader task: dispatch `message` events, starting with those already read
+/// in `early`, until the stream ends, then close the inbox so every in-flight
+/// request fails with a connection error.
+async fn read_events(mut resp: reqwest::Response, mut decoder: SseDecoder, early: Vec<SseEvent>, inbox: Inbox) {
+    let mut events = early;
+    loop {
+        for event in events {
+            if event.event == "message" {
+                dispatch_payload(&event.data, &inbox, None).await;
+            }
+        }
+        events = match resp.chunk().await {
+            Ok(Some(chunk)) => decoder.feed(&chunk),
+            _ => break,
+        };
+    }
+    inbox.close().await;
+    eprintln!("[McpHub][WARN] SSE stream for '{}' closed", inbox.server_name());
+}
+
+fn build_headers(headers: &HashMap<String, String>) -> Result<HeaderMap, String> {
+    let mut map = HeaderMap::new();
+    for (k, v) in headers {
+        let name = HeaderName::from_bytes(k.as_bytes()).map_err(|_| format!("Invalid header name: {}", k))?;
+        let value = HeaderValue::from_str(v).map_err(|_| format!("Invalid value for header {}", k))?;
+        map.insert(name, value);
+    }
+    Ok(map)
+}
+
+// ─── SSE Decoding ────────────────────────────────────────────
+
+#[derive(Debug, PartialEq)]
+pub struct SseEvent {
+    pub event: String,
+    pub data: String,
+}
+
+/// Incremental `text/event-stream` parser. Feed raw chunks, get complete events.
+#[derive(Default)]
+pub struct SseDecoder {
+    buf: Vec<u8>,
+    event: String,
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buf.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        while let Some(pos) = self.buf.iter().position(|&b| b == b'\n') {
+            let raw: Vec<u8> = self.buf.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&raw);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    let event = if self.event.is_empty() { "message".to_string() } else { std::mem::take(&mut self.event) };
+                    events.push(SseEvent { event, data: self.data.join("\n") });
+                }
+                self.event.clear();
+                self.data.clear();
+                continue;
+            }
+            if line.starts_with(':') {
+                continue; // comment / keepalive
+            }
+
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => self.event = value.to_string(),
+                "data" => self.data.push(value.to_string()),
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_decoder_split_chunks() {
+        let mut dec = SseDecoder::default();
+        assert!(dec.feed(b"event: mess").is_empty());
+        assert!(dec.feed(b"age\ndata: {\"id\":1}\r\n").is_empty());
+        let events = dec.feed(b"\r\n");
+        assert_eq!(events, vec![SseEvent { event: "message".into(), data: "{\"id\":1}".into() }]);
+    }
+
+    #[test]
+    fn test_sse_decoder_defaults_and_comments() {
+        let mut dec = SseDecoder::default();
+        let events = dec.feed(b": keepalive\n\ndata: a\ndata: b\n\nevent: endpoint\ndata: /m?x=1\n\n");
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event, "message");
+        assert_eq!(events[0].data, "a\nb");
+        assert_eq!(events[1].event, "endpoint");
+        assert_eq!(events[1].data, "/m?x=1");
+    }
+}
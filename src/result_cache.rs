@@ -0,0 +1,172 @@
+//! Results of read-only tool calls, kept for a short TTL (`"cache"` in a
+//! server's config). Agents often repeat the same lookup seconds apart; a
+//! repeat within the TTL is answered here without reaching the server, and
+//! says so in the result's `_meta`.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+/// Beyond this many entries, expired ones are dropped, then the oldest.
+const MAX_ENTRIES: usize = 1000;
+
+/// `_meta` key of a cached result: `{"hit": true, "ageSecs": 12, "ttlSecs": 60}`.
+pub const META_KEY: &str = "mcphub/cache";
+
+struct Entry {
+    server: String,
+    result: Value,
+    stored: Instant,
+    ttl: Duration,
+}
+
+/// Cache use of one server, for metrics.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Stats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<String, Entry>,
+    stats: HashMap<String, Stats>,
+}
+
+#[derive(Default)]
+pub struct ResultCache {
+    inner: Mutex<Inner>,
+}
+
+/// Cache key of a call. Arguments are compared by value, whatever their key order.
+pub fn key(server: &str, tool: &str, arguments: &Value) -> String {
+    let mut key = format!("{}\0{}\0", server, tool);
+    write_canonical(&mut key, arguments);
+    key
+}
+
+fn write_canonical(out: &mut String, value: &Value) {
+    match value {
+        Value::Object(obj) => {
+            let mut keys: Vec<&String> = obj.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, k) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(k.clone()).to_string());
+                out.push(':');
+                write_canonical(out, &obj[k]);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(out, item);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+impl ResultCache {
+    /// The cached result for `key`, marked as such, if it is still fresh.
+    /// Counts a hit or a miss for `server`.
+    pub fn get(&self, server: &str, key: &str) -> Option<Value> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let fresh = inner.entries.get(key).is_some_and(|e| e.stored.elapsed() < e.ttl);
+        if !fresh {
+            inner.entries.remove(key);
+            inner.stats.entry(server.to_string()).or_default().misses += 1;
+            return None;
+        }
+        let entry = &inner.entries[key];
+        let age = entry.stored.elapsed();
+        let mut result = entry.result.clone();
+        let mark = serde_json::json!({ "hit": true, "ageSecs": age.as_secs(), "ttlSecs": entry.ttl.as_secs() });
+        if let Some(obj) = result.as_object_mut() {
+            let meta = obj.entry("_meta").or_insert_with(|| serde_json::json!({}));
+            if let Some(meta) = meta.as_object_mut() {
+                meta.insert(META_KEY.into(), mark);
+            }
+        }
+        inner.stats.entry(server.to_string()).or_default().hits += 1;
+        Some(result)
+    }
+
+    /// Remember a result fetched from the server after a miss.
+    pub fn insert(&self, server: &str, key: String, result: &Value, ttl: Duration) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if inner.entries.len() >= MAX_ENTRIES {
+            inner.entries.retain(|_, e| e.stored.elapsed() < e.ttl);
+        }
+        if inner.entries.len() >= MAX_ENTRIES {
+            let oldest = inner.entries.iter().min_by_key(|(_, e)| e.stored).map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                inner.entries.remove(&oldest);
+            }
+        }
+        let entry = Entry { server: server.to_string(), result: result.clone(), stored: Instant::now(), ttl };
+        inner.entries.insert(key, entry);
+    }
+
+    /// Drop every cached result of `server`. Returns how many there were.
+    pub fn invalidate(&self, server: &str) -> usize {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let before = inner.entries.len();
+        inner.entries.retain(|_, e| e.server != server);
+        before - inner.entries.len()
+    }
+
+    pub fn stats(&self) -> Vec<(String, Stats)> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let mut stats = inner.stats.clone();
+        for entry in inner.entries.values() {
+            if entry.stored.elapsed() < entry.ttl {
+                stats.entry(entry.server.clone()).or_default().entries += 1;
+            }
+        }
+        stats.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_cache_hit_expiry_and_invalidation() {
+        let a = key("fs", "read", &json!({ "path": "/a", "opts": { "x": 1, "y": [2, { "b": 1, "a": 0 }] } }));
+        let b = key("fs", "read", &json!({ "opts": { "y": [2, { "a": 0, "b": 1 }], "x": 1 }, "path": "/a" }));
+        assert_eq!(a, b);
+        assert_ne!(a, key("fs", "read", &json!({ "path": "/b" })));
+
+        let cache = ResultCache::default();
+        let result = json!({ "content": [{ "type": "text", "text": "hi" }], "_meta": { "vendor/x": 1 } });
+        assert!(cache.get("fs", &a).is_none());
+        cache.insert("fs", a.clone(), &result, Duration::from_secs(60));
+        let hit = cache.get("fs", &a).unwrap();
+        assert_eq!(hit["_meta"][META_KEY]["hit"], true);
+        assert_eq!(hit["_meta"]["vendor/x"], 1);
+
+        cache.insert("fs", "expired".into(), &result, Duration::ZERO);
+        assert!(cache.get("fs", "expired").is_none());
+
+        assert_eq!(cache.stats(), vec![("fs".to_string(), Stats { hits: 1, misses: 2, entries: 1 })]);
+        assert_eq!(cache.invalidate("fs"), 1);
+        assert!(cache.get("fs", &a).is_none());
+        // A miss whose call then fails is never inserted, but still counts
+        assert!(cache.get("fs", "failed").is_none());
+        assert_eq!(cache.stats(), vec![("fs".to_string(), Stats { hits: 1, misses: 4, entries: 0 })]);
+    }
+}
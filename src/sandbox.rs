@@ -0,0 +1,288 @@
+//! Confinement of untrusted stdio servers (`"sandbox"` in config), Linux only.
+//! Path allowlists are enforced with Landlock; `"network": false` installs a
+//! seccomp filter refusing internet sockets. Everything is prepared in McpHub
+//! and applied in the child between fork and exec, so a server never runs
+//! unconfined. If the kernel can't enforce what is configured, the server is
+//! not started at all.
+
+use crate::config::Sandbox;
+
+/// Read (and execute) access every confined server gets, so interpreters,
+/// shared libraries and /etc keep working. Missing ones are skipped.
+const SYSTEM_READ_ONLY: &[&str] = &[
+    "/usr", "/bin", "/sbin", "/lib", "/lib32", "/lib64", "/etc", "/opt", "/nix", "/proc", "/sys", "/dev",
+];
+/// Writable in every confined server.
+const SYSTEM_READ_WRITE: &[&str] = &["/dev/null"];
+
+/// Landlock ABI version of the running kernel, or None without Landlock.
+#[cfg(target_os = "linux")]
+pub fn landlock_abi() -> Option<u32> {
+    // SAFETY: the version query takes no pointers.
+    let abi = unsafe {
+        libc::syscall(libc::SYS_landlock_create_ruleset, std::ptr::null::<u8>(), 0usize, landlock::CREATE_RULESET_VERSION)
+    };
+    (abi > 0).then_some(abi as u32)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn landlock_abi() -> Option<u32> {
+    None
+}
+
+/// Whether seccomp filters (and an architecture we build them for) are available.
+pub fn seccomp_supported() -> bool {
+    cfg!(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))
+        && std::path::Path::new("/proc/sys/kernel/seccomp/actions_avail").exists()
+}
+
+/// Arrange for `sandbox` to be applied to the child `cmd` spawns for server `name`.
+#[cfg(target_os = "linux")]
+pub fn apply(name: &str, cmd: &mut tokio::process::Command, sandbox: &Sandbox) -> Result<(), String> {
+    let ruleset = if sandbox.restrict_paths {
+        let abi = landlock_abi().ok_or("sandbox paths need Landlock, which this kernel does not support")?;
+        Some(landlock::Ruleset::new(name, abi, sandbox))
+    } else {
+        None
+    };
+    let filter = if sandbox.network {
+        None
+    } else if seccomp_supported() {
+        Some(seccomp::deny_inet_filter())
+    } else {
+        return Err("\"network\": false needs seccomp filters, which are not available here".into());
+    };
+    if ruleset.is_none() && filter.is_none() {
+        return Ok(());
+    }
+
+    // SAFETY: the closure runs in the forked child. It only makes syscalls
+    // (open, close, prctl, landlock_*) on memory prepared before the fork.
+    unsafe {
+        cmd.pre_exec(move || {
+            if let Some(ruleset) = &ruleset {
+                ruleset.restrict_self()?;
+            }
+            if let Some(filter) = &filter {
+                seccomp::install(filter)?;
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn apply(_name: &str, _cmd: &mut tokio::process::Command, _sandbox: &Sandbox) -> Result<(), String> {
+    Err("sandbox is only supported on Linux".into())
+}
+
+#[cfg(target_os = "linux")]
+fn no_new_privs() -> std::io::Result<()> {
+    // SAFETY: plain prctl with integer arguments.
+    if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod landlock {
+    use std::ffi::CString;
+    use std::io;
+
+    use super::{no_new_privs, SYSTEM_READ_ONLY, SYSTEM_READ_WRITE};
+    use crate::config::Sandbox;
+
+    pub const CREATE_RULESET_VERSION: u32 = 1;
+    const RULE_PATH_BENEATH: libc::c_int = 1;
+
+    const EXECUTE: u64 = 1 << 0;
+    const WRITE_FILE: u64 = 1 << 1;
+    const READ_FILE: u64 = 1 << 2;
+    const READ_DIR: u64 = 1 << 3;
+    /// REMOVE_DIR through MAKE_SYM (ABI 1).
+    const MODIFY_DIR: u64 = 0b1_1111_1111 << 4;
+    const REFER: u64 = 1 << 13;
+    const TRUNCATE: u64 = 1 << 14;
+    const IOCTL_DEV: u64 = 1 << 15;
+    /// Rights that may be granted on a file rather than a directory.
+    const FILE_RIGHTS: u64 = EXECUTE | WRITE_FILE | READ_FILE | TRUNCATE | IOCTL_DEV;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+        handled_access_net: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: libc::c_int,
+    }
+
+    /// Filesystem rights the kernel's ABI knows about; all of them are restricted.
+    fn handled(abi: u32) -> u64 {
+        let mut rights = EXECUTE | WRITE_FILE | READ_FILE | READ_DIR | MODIFY_DIR;
+        if abi >= 2 {
+            rights |= REFER;
+        }
+        if abi >= 3 {
+            rights |= TRUNCATE;
+        }
+        if abi >= 5 {
+            rights |= IOCTL_DEV;
+        }
+        rights
+    }
+
+    /// Paths and the rights granted beneath each, resolved before the fork.
+    pub struct Ruleset {
+        handled: u64,
+        rules: Vec<(CString, u64)>,
+    }
+
+    impl Ruleset {
+        pub fn new(name: &str, abi: u32, sandbox: &Sandbox) -> Self {
+            let handled = handled(abi);
+            let read = (EXECUTE | READ_FILE | READ_DIR | IOCTL_DEV) & handled;
+            let read_only = SYSTEM_READ_ONLY.iter().copied().chain(sandbox.read_only.iter().map(String::as_str));
+            let read_write = SYSTEM_READ_WRITE.iter().copied().chain(sandbox.read_write.iter().map(String::as_str));
+
+            let mut rules = Vec::new();
+            for (path, rights) in read_only.map(|p| (p, read)).chain(read_write.map(|p| (p, handled))) {
+                let meta = match std::fs::metadata(path) {
+                    Ok(meta) => meta,
+                    Err(_) => {
+                        if !SYSTEM_READ_ONLY.contains(&path) && !SYSTEM_READ_WRITE.contains(&path) {
+                            eprintln!("[McpHub][WARN] Sandbox path for {} does not exist, skipping: {}", name, path);
+                        }
+                        continue;
+                    }
+                };
+                let rights = if meta.is_dir() { rights } else { rights & FILE_RIGHTS };
+                if let Ok(path) = CString::new(path) {
+                    rules.push((path, rights));
+                }
+            }
+            Self { handled, rules }
+        }
+
+        /// Confine the calling process. Runs in the child after fork.
+        pub fn restrict_self(&self) -> io::Result<()> {
+            let attr = RulesetAttr { handled_access_fs: self.handled, handled_access_net: 0 };
+            // SAFETY: `attr` and each rule outlive the syscalls that read them,
+            // and every fd opened here is closed again.
+            unsafe {
+                let ruleset = libc::syscall(
+                    libc::SYS_landlock_create_ruleset,
+                    &attr as *const RulesetAttr,
+                    std::mem::size_of::<RulesetAttr>(),
+                    0u32,
+                ) as libc::c_int;
+                if ruleset < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                for (path, rights) in &self.rules {
+                    let fd = libc::open(path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC);
+                    if fd < 0 {
+                        continue;
+                    }
+                    let rule = PathBeneathAttr { allowed_access: *rights, parent_fd: fd };
+                    let added = libc::syscall(
+                        libc::SYS_landlock_add_rule,
+                        ruleset,
+                        RULE_PATH_BENEATH,
+                        &rule as *const PathBeneathAttr,
+                        0u32,
+                    );
+                    libc::close(fd);
+                    if added != 0 {
+                        let err = io::Error::last_os_error();
+                        libc::close(ruleset);
+                        return Err(err);
+                    }
+                }
+                no_new_privs()?;
+                let restricted = libc::syscall(libc::SYS_landlock_restrict_self, ruleset, 0u32);
+                let err = io::Error::last_os_error();
+                libc::close(ruleset);
+                if restricted != 0 {
+                    return Err(err);
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod seccomp {
+    use std::io;
+
+    use super::no_new_privs;
+
+    /// BPF_LD | BPF_W | BPF_ABS
+    const BPF_LD_W_ABS: u16 = 0x20;
+    /// BPF_JMP | BPF_JEQ | BPF_K
+    const BPF_JMP_JEQ_K: u16 = 0x15;
+    /// BPF_JMP | BPF_JGE | BPF_K
+    const BPF_JMP_JGE_K: u16 = 0x35;
+    /// BPF_RET | BPF_K
+    const BPF_RET_K: u16 = 0x06;
+
+    /// Offsets into `struct seccomp_data`; args[0] is read as its low 32 bits (little-endian).
+    const DATA_NR: u32 = 0;
+    const DATA_ARCH: u32 = 4;
+    const DATA_ARG0: u32 = 16;
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xC000_003E;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xC000_00B7;
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    const AUDIT_ARCH: u32 = 0;
+
+    /// x32 syscalls on x86_64 carry this bit; they are refused outright.
+    const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+    fn stmt(code: u16, k: u32) -> libc::sock_filter {
+        libc::sock_filter { code, jt: 0, jf: 0, k }
+    }
+
+    fn jump(code: u16, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+        libc::sock_filter { code, jt, jf, k }
+    }
+
+    /// Refuse `socket(AF_INET | AF_INET6, ...)` with EACCES. Unix sockets keep
+    /// working. io_uring is refused too, as it can open sockets without `socket`.
+    pub fn deny_inet_filter() -> Vec<libc::sock_filter> {
+        let errno = |e: i32| libc::SECCOMP_RET_ERRNO | (e as u32);
+        vec![
+            /* 0 */ stmt(BPF_LD_W_ABS, DATA_ARCH),
+            /* 1 */ jump(BPF_JMP_JEQ_K, AUDIT_ARCH, 1, 0),
+            /* 2 */ stmt(BPF_RET_K, errno(libc::EPERM)),
+            /* 3 */ stmt(BPF_LD_W_ABS, DATA_NR),
+            /* 4 */ jump(BPF_JMP_JGE_K, X32_SYSCALL_BIT, 7, 0),
+            /* 5 */ jump(BPF_JMP_JEQ_K, libc::SYS_io_uring_setup as u32, 6, 0),
+            /* 6 */ jump(BPF_JMP_JEQ_K, libc::SYS_socket as u32, 1, 0),
+            /* 7 */ stmt(BPF_RET_K, libc::SECCOMP_RET_ALLOW),
+            /* 8 */ stmt(BPF_LD_W_ABS, DATA_ARG0),
+            /* 9 */ jump(BPF_JMP_JEQ_K, libc::AF_INET as u32, 2, 0),
+            /* 10 */ jump(BPF_JMP_JEQ_K, libc::AF_INET6 as u32, 1, 0),
+            /* 11 */ stmt(BPF_RET_K, libc::SECCOMP_RET_ALLOW),
+            /* 12 */ stmt(BPF_RET_K, errno(libc::EACCES)),
+        ]
+    }
+
+    /// Install `filter` on the calling process. Runs in the child after fork.
+    pub fn install(filter: &[libc::sock_filter]) -> io::Result<()> {
+        no_new_privs()?;
+        let prog = libc::sock_fprog { len: filter.len() as u16, filter: filter.as_ptr() as *mut libc::sock_filter };
+        // SAFETY: `prog` points at `filter`, which outlives the call; the kernel copies it.
+        if unsafe { libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER, &prog as *const libc::sock_fprog) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
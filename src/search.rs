@@ -108,6 +108,16 @@ impl SearchEngine {
         );
     }
 
+    /// Swap one server's tools for `tools`, keeping every other server's entries.
+    pub fn replace_server(&mut self, server: &str, tools: Vec<IndexedTool>) {
+        let mut all: Vec<IndexedTool> = std::mem::take(&mut self.tools)
+            .into_iter()
+            .filter(|t| t.server_name != server)
+            .collect();
+        all.extend(tools);
+        self.build_index(all);
+    }
+
     /// Search tools by natural language query.
     /// Returns top-K results sorted by BM25 relevance.
     /// Typically <0.05ms for 200 tools in release mode.
@@ -259,6 +269,7 @@ mod tests {
                 name: name.to_string(),
                 description: desc.to_string(),
                 input_schema: json!({"type": "object"}),
+                ..Default::default()
             },
         }
     }
@@ -301,6 +312,20 @@ mod tests {
         assert_eq!(results2[0].original_name, "write_file");
     }
 
+    #[test]
+    fn test_replace_server() {
+        let mut engine = SearchEngine::new();
+        let mut other = mock_tool("list_issues", "List issues in a repository");
+        other.server_name = "github".to_string();
+        engine.build_index(vec![mock_tool("read_file", "Reads a file from the disk"), other]);
+
+        engine.replace_server("test", vec![mock_tool("deploy", "Deploys the site")]);
+        assert_eq!(engine.tool_count(), 2);
+        assert!(engine.find_tool("test", "read_file").is_none());
+        assert!(engine.find_tool("test", "deploy").is_some());
+        assert_eq!(engine.search("issues", 5)[0].server_name, "github");
+    }
+
     #[test]
     fn test_empty_search() {
         let mut engine = SearchEngine::new();
@@ -0,0 +1,256 @@
+//! Secret references in server `env` and `headers`, and the local secret store.
+//! A value may contain `${env:NAME}` (McpHub's environment), `${file:path}`
+//! (file contents, trailing newline dropped) or `${secret:name}` (the store,
+//! managed with `McpHub secret`). References stay in config.json as written
+//! and are resolved only when a server is spawned or connected.
+//!
+//! The store is ~/.McpHub/secrets.json, each value sealed with AES-256-GCM
+//! under the key in ~/.McpHub/secrets.key (both 0600). Keeping the key out of
+//! config.json means exporting, sharing or backing up the config leaks nothing.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+
+fn mcphub_dir() -> PathBuf {
+    dirs::home_dir().unwrap_or_default().join(".McpHub")
+}
+
+fn store_path() -> PathBuf {
+    mcphub_dir().join("secrets.json")
+}
+
+fn key_path() -> PathBuf {
+    mcphub_dir().join("secrets.key")
+}
+
+// ─── References ──────────────────────────────────────────────
+
+/// Replace every `${env:..}`, `${file:..}` and `${secret:..}` in `value`.
+/// Anything else, including `${VAR}` without a kind, is left untouched.
+pub fn resolve(value: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let reference = after.find('}').map(|end| &after[..end]);
+        let resolved = match reference.and_then(|r| r.split_once(':')) {
+            Some(("env", name)) => {
+                Some(std::env::var(name).map_err(|_| format!("${{env:{}}}: not set in McpHub's environment", name))?)
+            }
+            Some(("file", path)) => {
+                let path = expand_home(path);
+                let content = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("${{file:{}}}: {}", path.display(), e))?;
+                Some(content.trim_end_matches(['\n', '\r']).to_string())
+            }
+            Some(("secret", name)) => Some(get(name)?),
+            _ => None,
+        };
+        match (resolved, reference) {
+            (Some(resolved), Some(reference)) => {
+                out.push_str(&resolved);
+                rest = &after[reference.len() + 1..];
+            }
+            _ => {
+                out.push_str("${");
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// `resolve` every value of an `env` or `headers` map.
+pub fn resolve_map(map: &HashMap<String, String>) -> Result<HashMap<String, String>, String> {
+    map.iter()
+        .map(|(k, v)| resolve(v).map(|v| (k.clone(), v)).map_err(|e| format!("{}: {}", k, e)))
+        .collect()
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+// ─── Store ───────────────────────────────────────────────────
+
+/// Name → hex(nonce ‖ ciphertext ‖ tag). Names are not secret and stay readable.
+fn read_store() -> BTreeMap<String, String> {
+    std::fs::read_to_string(store_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn write_store(store: &BTreeMap<String, String>) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(store).map_err(|e| e.to_string())?;
+    write_private(&store_path(), json.as_bytes())
+}
+
+/// Write a file only the user can read, creating ~/.McpHub if needed.
+fn write_private(path: &Path, content: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    file.write_all(content).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+}
+
+/// The store key; a new one is generated on first `set`.
+fn load_key(create: bool) -> Result<LessSafeKey, String> {
+    let path = key_path();
+    let bytes = match std::fs::read_to_string(&path) {
+        Ok(hex) => from_hex(hex.trim()).ok_or_else(|| format!("{} is corrupt", path.display()))?,
+        Err(_) if create => {
+            let mut bytes = vec![0u8; AES_256_GCM.key_len()];
+            SystemRandom::new().fill(&mut bytes).map_err(|_| "No system randomness available".to_string())?;
+            write_private(&path, to_hex(&bytes).as_bytes())?;
+            bytes
+        }
+        Err(_) => return Err("no secrets stored yet (McpHub secret set <name>)".into()),
+    };
+    let key = UnboundKey::new(&AES_256_GCM, &bytes).map_err(|_| format!("{} is corrupt", path.display()))?;
+    Ok(LessSafeKey::new(key))
+}
+
+pub fn set(name: &str, value: &str) -> Result<(), String> {
+    let key = load_key(true)?;
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new().fill(&mut nonce).map_err(|_| "No system randomness available".to_string())?;
+    let mut sealed = value.as_bytes().to_vec();
+    key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(name.as_bytes()), &mut sealed)
+        .map_err(|_| "Encryption failed".to_string())?;
+
+    let mut blob = nonce.to_vec();
+    blob.extend_from_slice(&sealed);
+    let mut store = read_store();
+    store.insert(name.to_string(), to_hex(&blob));
+    write_store(&store)
+}
+
+pub fn get(name: &str) -> Result<String, String> {
+    let blob = read_store()
+        .get(name)
+        .and_then(|hex| from_hex(hex))
+        .ok_or_else(|| format!("no secret named '{}'", name))?;
+    if blob.len() < NONCE_LEN {
+        return Err(format!("secret '{}' is corrupt", name));
+    }
+    let key = load_key(false)?;
+    let (nonce, sealed) = blob.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| format!("secret '{}' is corrupt", name))?;
+    let mut sealed = sealed.to_vec();
+    let plain = key
+        .open_in_place(nonce, Aad::from(name.as_bytes()), &mut sealed)
+        .map_err(|_| format!("secret '{}' cannot be decrypted (was secrets.key replaced?)", name))?;
+    String::from_utf8(plain.to_vec()).map_err(|_| format!("secret '{}' is not UTF-8", name))
+}
+
+pub fn list() -> Vec<String> {
+    read_store().into_keys().collect()
+}
+
+/// Whether a secret by that name existed.
+pub fn remove(name: &str) -> Result<bool, String> {
+    let mut store = read_store();
+    if store.remove(name).is_none() {
+        return Ok(false);
+    }
+    write_store(&store)?;
+    Ok(true)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
+
+// ─── CLI ─────────────────────────────────────────────────────
+
+const USAGE: &str = "Usage: McpHub secret set <name> [value] | get <name> | list | rm <name>";
+
+/// `McpHub secret ...`. Without a value, `set` reads it from stdin so it
+/// doesn't end up in shell history.
+pub fn run(args: &[String]) {
+    let arg = |i: usize| args.get(i).map(|s| s.as_str());
+    let result = match (arg(0), arg(1)) {
+        (Some("set"), Some(name)) => {
+            let value = match arg(2) {
+                Some(v) => v.to_string(),
+                None => {
+                    eprint!("Value for {}: ", name);
+                    let mut input = String::new();
+                    let _ = std::io::stdin().read_line(&mut input);
+                    input.trim_end_matches(['\n', '\r']).to_string()
+                }
+            };
+            set(name, &value).map(|_| eprintln!("Stored secret '{}'. Reference it as ${{secret:{}}}", name, name))
+        }
+        (Some("get"), Some(name)) => get(name).map(|v| println!("{}", v)),
+        (Some("list"), None) => {
+            let names = list();
+            if names.is_empty() {
+                eprintln!("No secrets stored.");
+            }
+            for name in names {
+                println!("{}", name);
+            }
+            Ok(())
+        }
+        (Some("rm"), Some(name)) => match remove(name) {
+            Ok(true) => {
+                eprintln!("Removed secret '{}'.", name);
+                Ok(())
+            }
+            Ok(false) => Err(format!("no secret named '{}'", name)),
+            Err(e) => Err(e),
+        },
+        _ => Err(USAGE.to_string()),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_references() {
+        let path = std::env::var("PATH").unwrap();
+        assert_eq!(resolve("a ${env:PATH} b").unwrap(), format!("a {} b", path));
+        assert_eq!(resolve("${HOME} ${other:x} ${unterminated").unwrap(), "${HOME} ${other:x} ${unterminated");
+        assert!(resolve("${env:MCPHUB_TEST_SURELY_UNSET}").unwrap_err().contains("not set"));
+        assert!(resolve("${file:/nonexistent/token}").is_err());
+    }
+
+    #[test]
+    fn test_hex_roundtrip() {
+        let bytes = [0u8, 1, 0xab, 0xff];
+        assert_eq!(from_hex(&to_hex(&bytes)).unwrap(), bytes);
+        assert!(from_hex("abc").is_none() && from_hex("zz").is_none());
+    }
+}
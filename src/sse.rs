@@ -15,12 +15,15 @@ use tokio::io::AsyncWriteExt;
 use tokio::net::TcpStream;
 use tokio::sync::{mpsc, Mutex};
 
+use crate::peer::ClientPeer;
 use crate::protocol::JsonRpcRequest;
 use crate::proxy::ProxyServer;
 
 /// A single SSE client session.
 struct SseSession {
     tx: mpsc::Sender<String>,
+    /// Relays upstream requests to this client over the same stream.
+    peer: Arc<ClientPeer>,
     last_activity: Instant,
 }
 
@@ -52,7 +55,14 @@ impl SseManager {
     /// Handle GET /sse — establish long-lived SSE connection.
     /// Sends endpoint event, then streams responses until client disconnects.
     pub async fn handle_connect(&self, mut stream: TcpStream) {
-        let session_id = generate_session_id();
+        let session_id = match generate_session_id() {
+            Ok(id) => id,
+            Err(e) => {
+                eprintln!("[McpHub][ERROR] Cannot create SSE session: {}", e);
+                let _ = stream.write_all(&http_response(500, "Internal Server Error", r#"{"error":"Cannot create session"}"#)).await;
+                return;
+            }
+        };
 
         // Enable TCP keepalive to detect half-open connections.
         // OS will send probes after idle; dead peers detected in ~30-75s.
@@ -89,6 +99,7 @@ impl SseManager {
             sessions.insert(
                 session_id.clone(),
                 SseSession {
+                    peer: Arc::new(ClientPeer::sse(Some(tx.clone()))),
                     tx,
                     last_activity: Instant::now(),
                 },
@@ -135,6 +146,21 @@ impl SseManager {
         body: &str,
         proxy: &Arc<ProxyServer>,
     ) -> Vec<u8> {
+        let peer = self.sessions.lock().await.get(session_id).map(|s| s.peer.clone());
+
+        // The client answering a request we relayed from an upstream server
+        if let Ok(message) = serde_json::from_str::<serde_json::Value>(body) {
+            if crate::peer::is_response(&message) {
+                return match peer {
+                    Some(peer) => {
+                        peer.resolve(message).await;
+                        http_response(202, "Accepted", "{\"ok\":true}")
+                    }
+                    None => http_response(404, "Not Found", "{\"error\":\"Session not found\"}"),
+                };
+            }
+        }
+
         // Parse JSON-RPC request
         let request: JsonRpcRequest = match serde_json::from_str(body) {
             Ok(r) => r,
@@ -150,7 +176,7 @@ impl SseManager {
         let has_id = request.id.is_some();
 
         // Process through proxy
-        let response = proxy.handle_request(request).await;
+        let response = proxy.handle_request(request, peer.as_ref()).await;
 
         // Send response through SSE stream
         if let Some(resp) = response {
@@ -214,7 +240,7 @@ pub fn extract_session_id(path: &str) -> Option<String> {
 }
 
 /// Write bytes + flush. Returns Err if either fails.
-async fn write_and_flush(stream: &mut TcpStream, data: &[u8]) -> Result<(), ()> {
+pub(crate) async fn write_and_flush(stream: &mut TcpStream, data: &[u8]) -> Result<(), ()> {
     if stream.write_all(data).await.is_err() {
         return Err(());
     }
@@ -225,7 +251,7 @@ async fn write_and_flush(stream: &mut TcpStream, data: &[u8]) -> Result<(), ()>
 }
 
 /// Configure TCP keepalive on the socket to detect dead peers.
-fn configure_tcp_keepalive(stream: &TcpStream) {
+pub(crate) fn configure_tcp_keepalive(stream: &TcpStream) {
     use std::time::Duration;
     let sock_ref = socket2::SockRef::from(stream);
     let mut ka = socket2::TcpKeepalive::new()
@@ -257,17 +283,13 @@ async fn session_reaper(sessions: Arc<Mutex<HashMap<String, SseSession>>>) {
     }
 }
 
-/// Generate a random session ID (no uuid crate needed).
-fn generate_session_id() -> String {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let nanos = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_nanos();
-    let pid = std::process::id();
-    static COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
-    let count = COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-    format!("{:x}-{:x}-{:x}", nanos, pid, count)
+/// A session ID: 128 bits from the system CSPRNG, hex-encoded. The ID alone
+/// routes a client's messages, so it must not be guessable.
+pub(crate) fn generate_session_id() -> Result<String, String> {
+    use ring::rand::{SecureRandom, SystemRandom};
+    let mut bytes = [0u8; 16];
+    SystemRandom::new().fill(&mut bytes).map_err(|_| "No system randomness available".to_string())?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
 }
 
 fn http_response(status: u16, status_text: &str, body: &str) -> Vec<u8> {
@@ -0,0 +1,111 @@
+//! Per-server ring buffers of child stderr.
+//! Children used to run with stderr discarded, so a server crashing on startup
+//! only showed up as "Server closed connection". The last lines are kept here,
+//! attached to start/restart errors and health alerts, and served to the
+//! dashboard and `McpHub logs --stderr`.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// Lines kept per server (all instances of a pool share one buffer).
+const MAX_LINES: usize = 200;
+/// Longer lines are cut, so one minified stack trace can't eat the buffer.
+const MAX_LINE_CHARS: usize = 1000;
+/// Lines attached to error messages and alerts.
+pub const TAIL_LINES: usize = 10;
+
+#[derive(Default)]
+struct Ring {
+    lines: VecDeque<String>,
+    /// Lines ever pushed, so callers can ask for "everything since X".
+    total: u64,
+}
+
+#[derive(Default)]
+pub struct StderrBuffers {
+    servers: Mutex<HashMap<String, Ring>>,
+}
+
+impl StderrBuffers {
+    pub fn push(&self, server: &str, line: &str) {
+        let line = line.trim_end();
+        if line.is_empty() {
+            return;
+        }
+        let line: String = line.chars().take(MAX_LINE_CHARS).collect();
+        if let Ok(mut servers) = self.servers.lock() {
+            let ring = servers.entry(server.to_string()).or_default();
+            if ring.lines.len() == MAX_LINES {
+                ring.lines.pop_front();
+            }
+            ring.lines.push_back(line);
+            ring.total += 1;
+        }
+    }
+
+    /// Everything buffered for `server`, oldest first.
+    pub fn lines(&self, server: &str) -> Vec<String> {
+        self.servers
+            .lock()
+            .ok()
+            .and_then(|s| s.get(server).map(|r| r.lines.iter().cloned().collect()))
+            .unwrap_or_default()
+    }
+
+    /// Position marker: pass to `since` to get only lines written after now.
+    pub fn mark(&self, server: &str) -> u64 {
+        self.servers.lock().ok().and_then(|s| s.get(server).map(|r| r.total)).unwrap_or(0)
+    }
+
+    /// The last `max` lines written after `mark`.
+    pub fn since(&self, server: &str, mark: u64, max: usize) -> Vec<String> {
+        let servers = match self.servers.lock() {
+            Ok(s) => s,
+            Err(_) => return Vec::new(),
+        };
+        let ring = match servers.get(server) {
+            Some(r) => r,
+            None => return Vec::new(),
+        };
+        let new = ring.total.saturating_sub(mark).min(ring.lines.len() as u64) as usize;
+        let take = new.min(max);
+        ring.lines.iter().skip(ring.lines.len() - take).cloned().collect()
+    }
+
+    /// `message` followed by the given stderr lines, if there are any.
+    pub fn annotate(message: &str, lines: &[String]) -> String {
+        if lines.is_empty() {
+            return message.to_string();
+        }
+        format!("{}\nstderr:\n  {}", message, lines.join("\n  "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_is_bounded() {
+        let buf = StderrBuffers::default();
+        for i in 0..(MAX_LINES + 5) {
+            buf.push("s", &format!("line {}", i));
+        }
+        let lines = buf.lines("s");
+        assert_eq!(lines.len(), MAX_LINES);
+        assert_eq!(lines[0], "line 5");
+    }
+
+    #[test]
+    fn test_since_mark() {
+        let buf = StderrBuffers::default();
+        buf.push("s", "old");
+        let mark = buf.mark("s");
+        buf.push("s", "new 1");
+        buf.push("s", "");
+        buf.push("s", "new 2");
+        assert_eq!(buf.since("s", mark, 10), vec!["new 1", "new 2"]);
+        assert_eq!(buf.since("s", mark, 1), vec!["new 2"]);
+        assert!(buf.since("other", 0, 10).is_empty());
+    }
+}
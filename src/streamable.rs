@@ -0,0 +1,433 @@
+//! Streamable HTTP transport (MCP 2025-03-26) served on a single `/mcp` endpoint.
+//! - POST: one JSON-RPC message or a batch. Requests are answered with JSON, or with
+//!   an SSE stream when the client accepts `text/event-stream`. That stream also
+//!   carries the progress and server requests that belong to its requests.
+//! - GET: optional long-lived SSE stream for server-initiated messages not tied
+//!   to a request.
+//! - DELETE: end the session named by `Mcp-Session-Id`.
+//!
+//! Sessions are created by `initialize` and identified by the `Mcp-Session-Id` header.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use serde_json::Value;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, Mutex};
+
+use crate::peer::ClientPeer;
+use crate::protocol::JsonRpcRequest;
+use crate::proxy::ProxyServer;
+
+/// A single Streamable HTTP client session.
+struct StreamableSession {
+    /// Sender half of the GET stream, if the client opened one.
+    tx: Option<mpsc::Sender<String>>,
+    /// Relays upstream requests to this client, over the POST stream of the
+    /// request they belong to or else the GET stream.
+    peer: Arc<ClientPeer>,
+    last_activity: Instant,
+}
+
+/// Manages all Streamable HTTP sessions.
+pub struct StreamableManager {
+    sessions: Arc<Mutex<HashMap<String, StreamableSession>>>,
+}
+
+/// Max time a session can be idle before the reaper drops it (30 minutes).
+/// Longer than SSE: clients only talk to us when they have work to do.
+const SESSION_TIMEOUT_SECS: u64 = 1800;
+/// Reaper interval.
+const REAPER_INTERVAL_SECS: u64 = 60;
+/// Keepalive interval on GET streams.
+const KEEPALIVE_INTERVAL_SECS: u64 = 15;
+
+pub const SESSION_HEADER: &str = "mcp-session-id";
+const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+
+impl StreamableManager {
+    pub fn new() -> Self {
+        let manager = Self {
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+        };
+        let sessions_ref = manager.sessions.clone();
+        tokio::spawn(async move {
+            session_reaper(sessions_ref).await;
+        });
+        manager
+    }
+
+    /// Handle POST /mcp. Writes the full HTTP response to `stream`.
+    pub async fn handle_post(
+        &self,
+        mut stream: TcpStream,
+        headers: &HashMap<String, String>,
+        body: &str,
+        proxy: &Arc<ProxyServer>,
+    ) {
+        let parsed: Value = match serde_json::from_str(body) {
+            Ok(v) => v,
+            Err(e) => {
+                let resp = http_response(400, "Bad Request", None, &jsonrpc_error(-32700, &format!("Parse error: {}", e)));
+                return finish(stream, &resp).await;
+            }
+        };
+        let messages = match parsed {
+            Value::Array(items) => items,
+            other => vec![other],
+        };
+
+        // Only checked when sent: older clients don't send it
+        if let Some(version) = headers.get(PROTOCOL_VERSION_HEADER) {
+            if !crate::protocol::is_supported_version(version) {
+                let msg = format!("Unsupported MCP-Protocol-Version: {}", version);
+                let resp = http_response(400, "Bad Request", None, &jsonrpc_error(-32600, &msg));
+                return finish(stream, &resp).await;
+            }
+        }
+
+        let is_initialize = messages
+            .iter()
+            .any(|m| m.get("method").and_then(|v| v.as_str()) == Some("initialize"));
+
+        // Resolve or create the session
+        let (session_id, peer) = if is_initialize {
+            let id = match crate::sse::generate_session_id() {
+                Ok(id) => id,
+                Err(e) => {
+                    eprintln!("[McpHub][ERROR] Cannot create HTTP session: {}", e);
+                    let resp = http_response(500, "Internal Server Error", None, &jsonrpc_error(-32603, &e));
+                    return finish(stream, &resp).await;
+                }
+            };
+            let peer = Arc::new(ClientPeer::sse(None));
+            self.sessions.lock().await.insert(
+                id.clone(),
+                StreamableSession { tx: None, peer: peer.clone(), last_activity: Instant::now() },
+            );
+            eprintln!("[McpHub][HTTP] Session created: {}", id);
+            (id, peer)
+        } else {
+            let id = match headers.get(SESSION_HEADER) {
+                Some(id) => id.clone(),
+                None => {
+                    let resp = http_response(400, "Bad Request", None, &jsonrpc_error(-32600, "Missing Mcp-Session-Id header"));
+                    return finish(stream, &resp).await;
+                }
+            };
+            let peer = match self.sessions.lock().await.get_mut(&id) {
+                Some(session) => {
+                    session.last_activity = Instant::now();
+                    session.peer.clone()
+                }
+                None => {
+                    let resp = http_response(404, "Not Found", None, &jsonrpc_error(-32001, "Session not found"));
+                    return finish(stream, &resp).await;
+                }
+            };
+            (id, peer)
+        };
+
+        // Split into requests (need an answer) and everything else
+        let mut requests = Vec::new();
+        for msg in messages {
+            if crate::peer::is_response(&msg) {
+                peer.resolve(msg).await; // Client answering a relayed upstream request
+                continue;
+            }
+            match serde_json::from_value::<JsonRpcRequest>(msg) {
+                Ok(req) if req.id.is_some() => requests.push(req),
+                Ok(req) => {
+                    let _ = proxy.handle_request(req, Some(&peer)).await;
+                }
+                Err(_) => {}
+            }
+        }
+
+        if requests.is_empty() {
+            let resp = http_response(202, "Accepted", Some(&session_id), "");
+            return finish(stream, &resp).await;
+        }
+
+        let wants_sse = headers
+            .get("accept")
+            .map(|a| a.contains("text/event-stream"))
+            .unwrap_or(false);
+
+        if wants_sse {
+            // Stream each response as soon as it is ready, then close
+            let head = format!(
+                "HTTP/1.1 200 OK\r\n\
+                 Content-Type: text/event-stream\r\n\
+                 Cache-Control: no-cache\r\n\
+                 Mcp-Session-Id: {}\r\n\
+                 Access-Control-Allow-Origin: *\r\n\
+                 Access-Control-Expose-Headers: Mcp-Session-Id\r\n\
+                 Connection: close\r\n\
+                 \r\n",
+                session_id
+            );
+            if stream.write_all(head.as_bytes()).await.is_err() {
+                return;
+            }
+            // Attached for as long as the requests run, so their progress and
+            // sampling/elicitation requests reach a client without a GET stream
+            let (tx, mut rx) = mpsc::channel::<String>(64);
+            let ids: Vec<Value> = requests.iter().filter_map(|r| r.id.clone()).collect();
+            for id in &ids {
+                peer.attach_request_stream(id, tx.clone());
+            }
+            let answer = async {
+                for req in requests {
+                    if let Some(resp) = proxy.handle_request(req, Some(&peer)).await {
+                        let json = serde_json::to_string(&resp).unwrap_or_default();
+                        if tx.send(format!("event: message\ndata: {}\n\n", json)).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+                for id in &ids {
+                    peer.detach_request_stream(id);
+                }
+                drop(tx);
+            };
+            let write = async {
+                while let Some(event) = rx.recv().await {
+                    if crate::sse::write_and_flush(&mut stream, event.as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+                drop(rx);
+            };
+            tokio::join!(answer, write);
+            let _ = stream.shutdown().await;
+            return;
+        }
+
+        let mut responses = Vec::new();
+        for req in requests {
+            if let Some(resp) = proxy.handle_request(req, Some(&peer)).await {
+                responses.push(serde_json::to_value(&resp).unwrap_or(Value::Null));
+            }
+        }
+        let body = if responses.len() == 1 {
+            responses.remove(0).to_string()
+        } else {
+            Value::Array(responses).to_string()
+        };
+        let resp = http_response(200, "OK", Some(&session_id), &body);
+        finish(stream, &resp).await;
+    }
+
+    /// Handle GET /mcp — long-lived SSE stream for server-initiated messages.
+    pub async fn handle_get(&self, mut stream: TcpStream, headers: &HashMap<String, String>) {
+        let session_id = match headers.get(SESSION_HEADER) {
+            Some(id) => id.clone(),
+            None => {
+                let resp = http_response(400, "Bad Request", None, &jsonrpc_error(-32600, "Missing Mcp-Session-Id header"));
+                return finish(stream, &resp).await;
+            }
+        };
+
+        let (tx, mut rx) = mpsc::channel::<String>(64);
+        {
+            let mut sessions = self.sessions.lock().await;
+            match sessions.get_mut(&session_id) {
+                Some(session) => {
+                    session.peer.set_stream(Some(tx.clone()));
+                    session.tx = Some(tx);
+                    session.last_activity = Instant::now();
+                }
+                None => {
+                    drop(sessions);
+                    let resp = http_response(404, "Not Found", None, &jsonrpc_error(-32001, "Session not found"));
+                    return finish(stream, &resp).await;
+                }
+            }
+        }
+
+        crate::sse::configure_tcp_keepalive(&stream);
+        let head = "HTTP/1.1 200 OK\r\n\
+             Content-Type: text/event-stream\r\n\
+             Cache-Control: no-cache\r\n\
+             Connection: keep-alive\r\n\
+             Access-Control-Allow-Origin: *\r\n\
+             \r\n";
+        if stream.write_all(head.as_bytes()).await.is_err() {
+            return;
+        }
+
+        loop {
+            tokio::select! {
+                msg = rx.recv() => {
+                    match msg {
+                        Some(event) => {
+                            if crate::sse::write_and_flush(&mut stream, event.as_bytes()).await.is_err() {
+                                break;
+                            }
+                        }
+                        None => break, // Session deleted or reaped
+                    }
+                }
+                _ = tokio::time::sleep(std::time::Duration::from_secs(KEEPALIVE_INTERVAL_SECS)) => {
+                    if crate::sse::write_and_flush(&mut stream, b": keepalive\n\n").await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        // Stream gone, but the session itself stays valid for further POSTs
+        if let Some(session) = self.sessions.lock().await.get_mut(&session_id) {
+            session.peer.set_stream(None);
+            session.tx = None;
+        }
+        let _ = stream.shutdown().await;
+    }
+
+    /// Handle DELETE /mcp — client ends its session.
+    pub async fn handle_delete(&self, stream: TcpStream, headers: &HashMap<String, String>) {
+        let removed = match headers.get(SESSION_HEADER) {
+            Some(id) => self.sessions.lock().await.remove(id).is_some(),
+            None => false,
+        };
+        let resp = if removed {
+            eprintln!("[McpHub][HTTP] Session ended by client");
+            http_response(200, "OK", None, "")
+        } else {
+            http_response(404, "Not Found", None, &jsonrpc_error(-32001, "Session not found"))
+        };
+        finish(stream, &resp).await;
+    }
+
+    /// Get active session count.
+    pub async fn session_count(&self) -> usize {
+        self.sessions.lock().await.len()
+    }
+}
+
+async fn finish(mut stream: TcpStream, resp: &[u8]) {
+    let _ = stream.write_all(resp).await;
+    let _ = stream.shutdown().await;
+}
+
+fn jsonrpc_error(code: i64, message: &str) -> String {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": null,
+        "error": { "code": code, "message": message }
+    })
+    .to_string()
+}
+
+/// Periodically reap sessions with no activity for SESSION_TIMEOUT_SECS.
+async fn session_reaper(sessions: Arc<Mutex<HashMap<String, StreamableSession>>>) {
+    loop {
+        tokio::time::sleep(std::time::Duration::from_secs(REAPER_INTERVAL_SECS)).await;
+        let mut map = sessions.lock().await;
+        let stale: Vec<String> = map
+            .iter()
+            .filter(|(_, s)| s.tx.is_none() && s.last_activity.elapsed().as_secs() > SESSION_TIMEOUT_SECS)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &stale {
+            map.remove(id);
+            eprintln!("[McpHub][HTTP] Reaped stale session: {}", id);
+        }
+    }
+}
+
+fn http_response(status: u16, status_text: &str, session_id: Option<&str>, body: &str) -> Vec<u8> {
+    let session_header = session_id
+        .map(|id| format!("Mcp-Session-Id: {}\r\n", id))
+        .unwrap_or_default();
+    let content_type = if body.is_empty() { "" } else { "Content-Type: application/json\r\n" };
+    format!(
+        "HTTP/1.1 {} {}\r\n\
+         {}\
+         {}\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Expose-Headers: Mcp-Session-Id\r\n\
+         Connection: close\r\n\
+         \r\n\
+         {}",
+        status,
+        status_text,
+        content_type,
+        session_header,
+        body.len(),
+        body
+    )
+    .into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    /// Answers `initialize`, lists one tool, and reports progress before each result.
+    const UPSTREAM: &str = r#"while read -r line; do
+  id=$(echo "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  case "$line" in
+    *'"method":"initialize"'*) echo '{"jsonrpc":"2.0","id":'$id',"result":{"protocolVersion":"2025-06-18","capabilities":{"tools":{}},"serverInfo":{"name":"t","version":"1"}}}' ;;
+    *'"method":"tools/list"'*) echo '{"jsonrpc":"2.0","id":'$id',"result":{"tools":[{"name":"work","inputSchema":{"type":"object"}}]}}' ;;
+    *'"method":"tools/call"'*)
+      echo '{"jsonrpc":"2.0","method":"notifications/progress","params":{"progressToken":'$id',"progress":1,"total":2}}'
+      echo '{"jsonrpc":"2.0","id":'$id',"result":{"content":[{"type":"text","text":"done"}]}}' ;;
+  esac
+done"#;
+
+    /// POST `body` to the manager and return the raw HTTP response.
+    async fn post(manager: &StreamableManager, proxy: &Arc<ProxyServer>, headers: &[(&str, &str)], body: Value) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        let headers = headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        manager.handle_post(server, &headers, &body.to_string(), proxy).await;
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_progress_on_post_stream_without_get_stream() {
+        let server = crate::config::parse_server("t", &serde_json::json!({ "command": "sh", "args": ["-c", UPSTREAM] })).unwrap();
+        let config = crate::config::ProxyConfig {
+            servers: HashMap::from([("t".to_string(), server)]),
+            ..Default::default()
+        };
+        let proxy = Arc::new(ProxyServer::new(config));
+        let manager = StreamableManager::new();
+
+        let init = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "protocolVersion": "2025-06-18" } });
+        let response = post(&manager, &proxy, &[("accept", "application/json")], init).await;
+        let session = response
+            .lines()
+            .find_map(|l| l.strip_prefix("Mcp-Session-Id: "))
+            .expect("session header")
+            .to_string();
+
+        let call = serde_json::json!({
+            "jsonrpc": "2.0", "id": "call-1", "method": "tools/call",
+            "params": { "name": "execute", "arguments": { "server": "t", "tool": "work" }, "_meta": { "progressToken": "tok" } }
+        });
+        let headers = [("accept", "application/json, text/event-stream"), (SESSION_HEADER, session.as_str())];
+        let response = post(&manager, &proxy, &headers, call).await;
+        let events: Vec<Value> = response
+            .lines()
+            .filter_map(|l| l.strip_prefix("data: "))
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2, "{}", response);
+        assert_eq!(events[0]["method"], "notifications/progress");
+        assert_eq!(events[0]["params"]["progressToken"], "tok");
+        assert_eq!(events[1]["id"], "call-1");
+        assert_eq!(events[1]["result"]["content"][0]["text"], "done");
+        proxy.shutdown().await;
+    }
+}
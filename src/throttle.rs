@@ -0,0 +1,257 @@
+//! Call admission per server and per tool (`maxConcurrent`, `rateLimit`,
+//! `toolMaxConcurrent`, `toolRateLimits`), for upstream APIs that rate-limit
+//! hard. A call over a limit queues until a slot or token frees up, for at
+//! most `queueTimeout` seconds, then fails saying which limit held it back.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::config::{RateLimit, Throttle};
+
+/// Token bucket; tokens may go negative, each call reserving the next free slot.
+struct Bucket {
+    rate: RateLimit,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(rate: RateLimit) -> Self {
+        Self { rate, tokens: rate.burst as f64, updated: Instant::now() }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let earned = now.duration_since(self.updated).as_secs_f64() * self.rate.per_sec();
+        self.tokens = (self.tokens + earned).min(self.rate.burst as f64);
+        self.updated = now;
+    }
+
+    /// How long a call arriving now would wait for its token.
+    fn wait(&mut self) -> Duration {
+        self.refill();
+        Duration::from_secs_f64(((1.0 - self.tokens) / self.rate.per_sec()).max(0.0))
+    }
+
+    /// Take a token, returning how long to wait before it is really ours.
+    fn take(&mut self) -> Duration {
+        let wait = self.wait();
+        self.tokens -= 1.0;
+        wait
+    }
+}
+
+/// Limits for one server, or one tool of it.
+struct Gate {
+    label: String,
+    max_concurrent: Option<usize>,
+    semaphore: Option<Arc<Semaphore>>,
+    bucket: Option<Mutex<Bucket>>,
+}
+
+#[derive(Default)]
+struct Counters {
+    waiting: AtomicUsize,
+    throttled: AtomicU64,
+    rejected: AtomicU64,
+}
+
+/// Queue length and throttle counts of one server, for metrics.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Stats {
+    /// Calls queued right now.
+    pub waiting: usize,
+    /// Calls that had to wait for their turn.
+    pub throttled: u64,
+    /// Calls that gave up after `queueTimeout`.
+    pub rejected: u64,
+}
+
+/// Slots held by an admitted call, released when it is dropped.
+pub struct Permits {
+    _slots: Vec<OwnedSemaphorePermit>,
+}
+
+/// Counts a call as queued while alive.
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// (server, tool); no tool is the server-wide gate.
+type GateKey = (String, Option<String>);
+
+#[derive(Default)]
+pub struct Throttles {
+    gates: Mutex<HashMap<GateKey, Arc<Gate>>>,
+    counters: Mutex<HashMap<String, Arc<Counters>>>,
+}
+
+impl Throttles {
+    /// Wait until a call to `server` (and `tool`) is within its limits.
+    pub async fn acquire(&self, server: &str, tool: Option<&str>, config: &Throttle) -> Result<Permits, String> {
+        let gates = self.gates_for(server, tool, config);
+        if gates.is_empty() {
+            return Ok(Permits { _slots: Vec::new() });
+        }
+        let timeout = Duration::from_secs(config.queue_timeout_secs());
+        let deadline = Instant::now() + timeout;
+        let counters = self.counters_for(server);
+        counters.waiting.fetch_add(1, Ordering::Relaxed);
+        let _waiting = Waiting(&counters.waiting);
+
+        let mut waited = false;
+        let result = async {
+            // Tool slots first, so a call never holds a server slot while queued on its tool
+            let mut slots = Vec::new();
+            for gate in &gates {
+                let (Some(semaphore), Some(max)) = (&gate.semaphore, gate.max_concurrent) else { continue };
+                let slot = match semaphore.clone().try_acquire_owned() {
+                    Ok(slot) => slot,
+                    Err(_) => {
+                        waited = true;
+                        match tokio::time::timeout_at(deadline.into(), semaphore.clone().acquire_owned()).await {
+                            Ok(Ok(slot)) => slot,
+                            _ => {
+                                return Err(format!(
+                                    "Throttled: {} already runs {} call(s) at once (maxConcurrent {}), gave up after waiting {}s",
+                                    gate.label, max, max, timeout.as_secs()
+                                ))
+                            }
+                        }
+                    }
+                };
+                slots.push(slot);
+            }
+
+            // Rate limits: fail at once if the next token comes too late, else reserve and wait
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            for gate in &gates {
+                if let Some(bucket) = &gate.bucket {
+                    let mut bucket = bucket.lock().unwrap_or_else(|e| e.into_inner());
+                    let wait = bucket.wait();
+                    if wait > remaining {
+                        return Err(format!(
+                            "Throttled: {} is rate limited to {}, next call possible in {}s, beyond the {}s queue timeout",
+                            gate.label, bucket.rate, wait.as_secs_f64().ceil() as u64, timeout.as_secs()
+                        ));
+                    }
+                }
+            }
+            let mut wait = Duration::ZERO;
+            for gate in &gates {
+                if let Some(bucket) = &gate.bucket {
+                    wait = wait.max(bucket.lock().unwrap_or_else(|e| e.into_inner()).take());
+                }
+            }
+            if !wait.is_zero() {
+                waited = true;
+                tokio::time::sleep(wait).await;
+            }
+            Ok(Permits { _slots: slots })
+        }
+        .await;
+
+        if result.is_err() {
+            counters.rejected.fetch_add(1, Ordering::Relaxed);
+            eprintln!("[McpHub][WARN] {}", result.as_ref().err().map(String::as_str).unwrap_or_default());
+        }
+        if waited {
+            counters.throttled.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    /// The tool's gate (if it has limits) followed by the server's.
+    fn gates_for(&self, server: &str, tool: Option<&str>, config: &Throttle) -> Vec<Arc<Gate>> {
+        let mut gates = self.gates.lock().unwrap_or_else(|e| e.into_inner());
+        let mut found = Vec::new();
+        if let Some(tool) = tool {
+            let max = config.tool_max_concurrent.get(tool).copied();
+            let rate = config.tool_rate_limits.get(tool).copied();
+            if max.is_some() || rate.is_some() {
+                let key = (server.to_string(), Some(tool.to_string()));
+                let label = format!("{}/{}", server, tool);
+                found.push(gates.entry(key).or_insert_with(|| Gate::new(label, max, rate)).clone());
+            }
+        }
+        if config.max_concurrent.is_some() || config.rate_limit.is_some() {
+            let key = (server.to_string(), None);
+            let gate = gates.entry(key).or_insert_with(|| Gate::new(server.to_string(), config.max_concurrent, config.rate_limit));
+            found.push(gate.clone());
+        }
+        found
+    }
+
+    fn counters_for(&self, server: &str) -> Arc<Counters> {
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        counters.entry(server.to_string()).or_default().clone()
+    }
+
+    /// Drop `server`'s gates so changed limits take effect. Calls holding
+    /// slots of the old ones finish normally.
+    pub fn reset(&self, server: &str) {
+        self.gates.lock().unwrap_or_else(|e| e.into_inner()).retain(|(s, _), _| s != server);
+    }
+
+    pub fn stats(&self) -> Vec<(String, Stats)> {
+        let counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        counters
+            .iter()
+            .map(|(name, c)| {
+                let stats = Stats {
+                    waiting: c.waiting.load(Ordering::Relaxed),
+                    throttled: c.throttled.load(Ordering::Relaxed),
+                    rejected: c.rejected.load(Ordering::Relaxed),
+                };
+                (name.clone(), stats)
+            })
+            .collect()
+    }
+}
+
+impl Gate {
+    fn new(label: String, max_concurrent: Option<usize>, rate: Option<RateLimit>) -> Arc<Self> {
+        Arc::new(Self {
+            label,
+            max_concurrent,
+            semaphore: max_concurrent.map(|n| Arc::new(Semaphore::new(n))),
+            bucket: rate.map(|r| Mutex::new(Bucket::new(r))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RateLimit;
+
+    #[tokio::test]
+    async fn test_concurrency_and_rate_limits() {
+        let throttles = Throttles::default();
+        let config = Throttle { max_concurrent: Some(1), queue_timeout: Some(0), ..Default::default() };
+        let held = throttles.acquire("db", Some("query"), &config).await.unwrap();
+        let err = throttles.acquire("db", Some("query"), &config).await.err().unwrap();
+        assert!(err.contains("maxConcurrent 1"), "{}", err);
+        drop(held);
+        assert!(throttles.acquire("db", None, &config).await.is_ok());
+
+        let rate = RateLimit { count: 1, period_secs: 60, burst: 1 };
+        let config = Throttle { tool_rate_limits: HashMap::from([("search".to_string(), rate)]), ..config };
+        assert!(throttles.acquire("api", Some("search"), &config).await.is_ok());
+        assert!(throttles.acquire("api", Some("other"), &config).await.is_ok());
+        let err = throttles.acquire("api", Some("search"), &config).await.err().unwrap();
+        assert!(err.contains("api/search is rate limited to 1/min"), "{}", err);
+
+        let stats: HashMap<_, _> = throttles.stats().into_iter().collect();
+        assert_eq!(stats["db"], Stats { waiting: 0, throttled: 1, rejected: 1 });
+        assert_eq!(stats["api"].rejected, 1);
+    }
+}